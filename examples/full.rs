@@ -1,11 +1,11 @@
+use bevy::reflect::TypePath;
 use bevy::{
     diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
     prelude::*,
 };
-use bevy::reflect::TypePath;
 
-use bevy_ninepatch::*;
 use bevy_ninepatch::Size;
+use bevy_ninepatch::*;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     App::default()
@@ -38,6 +38,7 @@ fn setup(
                 original_size: IVec2::new(30, 35),
                 target_size: Size::new(Val::Px(0.), Val::Px(0.)),
                 content: None,
+                stretch: PatchStretch::default(),
             },
             // top middle-left patch. This patch width can grow, and will contain the content for
             // `PanelContent::Title`
@@ -45,24 +46,28 @@ fn setup(
                 original_size: IVec2::new(15, 35),
                 target_size: Size::new(Val::Percent(30.), Val::Px(0.)),
                 content: Some(Content::Title),
+                stretch: PatchStretch::default(),
             },
             // top middle patch. In the original PNG, it's the yellow titled part
             Patch {
                 original_size: IVec2::new(25, 35),
                 target_size: Size::new(Val::Px(0.), Val::Px(0.)),
                 content: None,
+                stretch: PatchStretch::default(),
             },
             // top middle-right patch. This patch width can grow
             Patch {
                 original_size: IVec2::new(20, 35),
                 target_size: Size::new(Val::Percent(70.), Val::Px(0.)),
                 content: None,
+                stretch: PatchStretch::default(),
             },
             // top right corner
             Patch {
                 original_size: IVec2::new(10, 35),
                 target_size: Size::new(Val::Px(0.), Val::Px(0.)),
                 content: None,
+                stretch: PatchStretch::default(),
             },
         ],
         vec![
@@ -71,18 +76,21 @@ fn setup(
                 original_size: IVec2::new(10, -45),
                 target_size: Size::new(Val::Px(0.), Val::Percent(100.)),
                 content: None,
+                stretch: PatchStretch::default(),
             },
             // center. This patch can grow both in height and width, and will contain `PanelContent::Body`
             Patch {
                 original_size: IVec2::new(-20, -45),
                 target_size: Size::new(Val::Percent(100.), Val::Percent(100.)),
                 content: Some(Content::Content),
+                stretch: PatchStretch::default(),
             },
             // right border. This patch height can grow
             Patch {
                 original_size: IVec2::new(10, -45),
                 target_size: Size::new(Val::Px(0.), Val::Percent(100.)),
                 content: None,
+                stretch: PatchStretch::default(),
             },
         ],
         vec![
@@ -91,18 +99,21 @@ fn setup(
                 original_size: IVec2::new(10, 10),
                 target_size: Size::new(Val::Px(0.), Val::Px(0.)),
                 content: None,
+                stretch: PatchStretch::default(),
             },
             // bottom middle. This patch width can grow
             Patch {
                 original_size: IVec2::new(-20, 10),
                 target_size: Size::new(Val::Percent(100.), Val::Px(0.)),
                 content: None,
+                stretch: PatchStretch::default(),
             },
             // bottom right corner
             Patch {
                 original_size: IVec2::new(10, 10),
                 target_size: Size::new(Val::Px(0.), Val::Px(0.)),
                 content: None,
+                stretch: PatchStretch::default(),
             },
         ],
     ]));
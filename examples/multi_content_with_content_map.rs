@@ -49,7 +49,7 @@ fn setup(
 
     // create a `HashMap` that will list all entities used as content of the 9-Patch UI element
     let mut button_content = std::collections::HashMap::new();
-    button_content.insert((), text_entity);
+    button_content.insert((), vec![text_entity]);
 
     let button_entity = commands
         .spawn(
@@ -0,0 +1,418 @@
+use bevy::{
+    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypePath,
+    render::{
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::{CompressedImageFormats, ImageType},
+    },
+};
+
+use crate::{ninepatch::*, NinePatchError};
+
+/// Label of the cropped texture loaded alongside the `NinePatchBuilder` of an Android 9-Patch image
+pub const ANDROID_TEXTURE_LABEL: &str = "texture";
+
+/// Asset loader for Android 9-Patch images (`.9.png`).
+///
+/// The default asset of the file is the `NinePatchBuilder` described by the guides, and the image
+/// without its guide border is available with the label [`ANDROID_TEXTURE_LABEL`]:
+/// `asset_server.load("panel.9.png#texture")`. The content zone uses `T::default()`.
+///
+/// This loader is not added by the `NinePatchPlugin`, register it with
+/// `app.init_asset_loader::<AndroidNinePatchLoader<T>>()`.
+#[derive(Debug, Clone, Copy)]
+pub struct AndroidNinePatchLoader<T> {
+    marker: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<T> Default for AndroidNinePatchLoader<T> {
+    fn default() -> Self {
+        AndroidNinePatchLoader {
+            marker: Default::default(),
+        }
+    }
+}
+
+impl<T: Clone + Send + Sync + Default + Eq + std::hash::Hash + TypePath + 'static> AssetLoader
+    for AndroidNinePatchLoader<T>
+{
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let image = Image::from_buffer(
+                bytes,
+                ImageType::Extension("png"),
+                CompressedImageFormats::NONE,
+                true,
+            )?;
+            let (texture, nine_patch) = NinePatchBuilder::from_android_image(&image, T::default())?;
+            load_context.set_labeled_asset(ANDROID_TEXTURE_LABEL, LoadedAsset::new(texture));
+            load_context.set_default_asset(LoadedAsset::new(nine_patch));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["9.png"]
+    }
+}
+
+/// Reads a guide line, returning for each pixel if it is marked
+fn read_guide(
+    data: &[u8],
+    width: u32,
+    pixels: impl Iterator<Item = (u32, u32)>,
+) -> Result<Vec<bool>, NinePatchError> {
+    pixels
+        .map(|(x, y)| {
+            let start = ((y * width + x) * 4) as usize;
+            match data[start..start + 4] {
+                [_, _, _, 0] | [255, 255, 255, 255] | [255, 0, 0, 255] => Ok(false),
+                [0, 0, 0, 255] => Ok(true),
+                _ => Err(NinePatchError::InvalidGuidePixel { x, y }),
+            }
+        })
+        .collect()
+}
+
+/// Range of pixels from the first to the last marked pixel of a guide line
+fn marked_range(guide: &[bool]) -> Option<std::ops::Range<usize>> {
+    let first = guide.iter().position(|m| *m)?;
+    let last = guide.iter().rposition(|m| *m)?;
+    Some(first..last + 1)
+}
+
+/// Splits a guide line in segments of consecutive pixels with the same mark, also starting a new
+/// segment at each edge of `content`
+fn to_segments(guide: &[bool], content: &std::ops::Range<usize>) -> Vec<(u32, bool)> {
+    let mut segments: Vec<(u32, bool)> = vec![];
+    for (index, &marked) in guide.iter().enumerate() {
+        let is_edge = index == content.start || index == content.end;
+        match segments.last_mut() {
+            Some((length, last)) if *last == marked && !is_edge => *length += 1,
+            _ => segments.push((1, marked)),
+        }
+    }
+    segments
+}
+
+/// Target sizes of each segment. Fixed segments keep their original size, and stretchable segments
+/// share the available space proportionally to their original size, as Android does
+fn to_target_sizes(segments: &[(u32, bool)]) -> Vec<Val> {
+    let stretch_total: u32 = segments
+        .iter()
+        .filter(|(_, stretch)| *stretch)
+        .map(|(length, _)| length)
+        .sum();
+    segments
+        .iter()
+        .map(|(length, stretch)| {
+            if *stretch {
+                Val::Percent(*length as f32 / stretch_total as f32 * 100.)
+            } else {
+                Val::Px(0.)
+            }
+        })
+        .collect()
+}
+
+/// Index of the segment covering `content`. When it covers several segments, the first
+/// stretchable one is used, or the first one if none is stretchable
+fn content_segment(content: &std::ops::Range<usize>, segments: &[(u32, bool)]) -> usize {
+    let mut start = 0;
+    let covered: Vec<(usize, bool)> = segments
+        .iter()
+        .enumerate()
+        .filter_map(|(index, (length, stretch))| {
+            let range = start..start + *length as usize;
+            start = range.end;
+            (content.start <= range.start && range.end <= content.end).then_some((index, *stretch))
+        })
+        .collect();
+    covered
+        .iter()
+        .find(|(_, stretch)| *stretch)
+        .or(covered.first())
+        .map(|(index, _)| *index)
+        .unwrap_or(0)
+}
+
+impl<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> NinePatchBuilder<T> {
+    /// Create a `NinePatchBuilder` from an Android 9-Patch image, and the image without its guides.
+    ///
+    /// The black pixels of the top and left guides mark the stretchable columns and rows, and each
+    /// contiguous run of pixels becomes a column or a row of patches. When there are several
+    /// stretchable regions on an axis, they grow proportionally to their size.
+    ///
+    /// The bottom and right guides mark the content area, or the stretchable area without them.
+    /// Columns and rows are also split at its edges, so the content zone is the patch covering
+    /// exactly the content area. When the content area covers several columns or rows, because it
+    /// spans both fixed and stretchable pixels, the content zone is its first stretchable column
+    /// or row.
+    pub fn from_android_image(image: &Image, content: T) -> Result<(Image, Self), NinePatchError> {
+        let Extent3d { width, height, .. } = image.texture_descriptor.size;
+        if width < 3 || height < 3 {
+            return Err(NinePatchError::ImageTooSmall { width, height });
+        }
+        let converted;
+        let image = if image.texture_descriptor.format == TextureFormat::Rgba8UnormSrgb {
+            image
+        } else {
+            converted = image
+                .convert(TextureFormat::Rgba8UnormSrgb)
+                .ok_or(NinePatchError::UnreadableImage)?;
+            &converted
+        };
+        let data = &image.data;
+
+        let columns_guide = read_guide(data, width, (1..width - 1).map(|x| (x, 0)))?;
+        let rows_guide = read_guide(data, width, (1..height - 1).map(|y| (0, y)))?;
+        let padding_columns_guide =
+            read_guide(data, width, (1..width - 1).map(|x| (x, height - 1)))?;
+        let padding_rows_guide = read_guide(data, width, (1..height - 1).map(|y| (width - 1, y)))?;
+
+        let content_columns = marked_range(&padding_columns_guide)
+            .or_else(|| marked_range(&columns_guide))
+            .unwrap_or(0..columns_guide.len());
+        let content_rows = marked_range(&padding_rows_guide)
+            .or_else(|| marked_range(&rows_guide))
+            .unwrap_or(0..rows_guide.len());
+        let columns = to_segments(&columns_guide, &content_columns);
+        let rows = to_segments(&rows_guide, &content_rows);
+        let column_sizes = to_target_sizes(&columns);
+        let row_sizes = to_target_sizes(&rows);
+        let content_column = content_segment(&content_columns, &columns);
+        let content_row = content_segment(&content_rows, &rows);
+
+        let patches = rows
+            .iter()
+            .zip(row_sizes)
+            .enumerate()
+            .map(|(row_index, ((row_height, _), target_height))| {
+                columns
+                    .iter()
+                    .zip(&column_sizes)
+                    .enumerate()
+                    .map(|(column_index, ((column_width, _), target_width))| Patch {
+                        original_size: IVec2::new(*column_width as i32, *row_height as i32),
+                        target_size: Size::new(*target_width, target_height),
+                        content: (row_index == content_row && column_index == content_column)
+                            .then(|| content.clone()),
+                        stretch: PatchStretch::default(),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut cropped_data = Vec::with_capacity(((width - 2) * (height - 2) * 4) as usize);
+        for y in 1..height - 1 {
+            let start = ((y * width + 1) * 4) as usize;
+            let end = ((y * width + width - 1) * 4) as usize;
+            cropped_data.extend_from_slice(&data[start..end]);
+        }
+        let mut texture = Image::new(
+            Extent3d {
+                width: width - 2,
+                height: height - 2,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            cropped_data,
+            TextureFormat::Rgba8UnormSrgb,
+        );
+        texture.sampler_descriptor = image.sampler_descriptor.clone();
+
+        Ok((texture, Self::from_patches(patches)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: [u8; 4] = [0, 0, 0, 255];
+
+    /// Image of `width`x`height` pixels filled with gray, with black guide pixels at `marks`
+    fn guide_image(width: u32, height: u32, marks: &[(u32, u32)]) -> Image {
+        let mut data = [128, 128, 128, 255].repeat((width * height) as usize);
+        for x in 0..width {
+            for y in [0, height - 1] {
+                let start = ((y * width + x) * 4) as usize;
+                data[start..start + 4].copy_from_slice(&[0, 0, 0, 0]);
+            }
+        }
+        for y in 0..height {
+            for x in [0, width - 1] {
+                let start = ((y * width + x) * 4) as usize;
+                data[start..start + 4].copy_from_slice(&[0, 0, 0, 0]);
+            }
+        }
+        for (x, y) in marks {
+            let start = ((y * width + x) * 4) as usize;
+            data[start..start + 4].copy_from_slice(&BLACK);
+        }
+        Image::new(
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+        )
+    }
+
+    fn content_patch(nine_patch: &NinePatchBuilder<()>) -> (usize, usize) {
+        let mut found = None;
+        for (row, patches) in nine_patch.patches.iter().enumerate() {
+            for (column, patch) in patches.iter().enumerate() {
+                if patch.content.is_some() {
+                    assert!(found.is_none(), "several content zones");
+                    found = Some((row, column));
+                }
+            }
+        }
+        found.expect("no content zone")
+    }
+
+    fn widths(nine_patch: &NinePatchBuilder<()>) -> Vec<(i32, Val)> {
+        nine_patch.patches[0]
+            .iter()
+            .map(|patch| (patch.original_size.x, patch.target_size.width))
+            .collect()
+    }
+
+    #[test]
+    fn too_small() {
+        let image = guide_image(2, 5, &[]);
+        assert_eq!(
+            NinePatchBuilder::from_android_image(&image, ()).unwrap_err(),
+            NinePatchError::ImageTooSmall {
+                width: 2,
+                height: 5
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_guide_pixel() {
+        let mut image = guide_image(6, 6, &[]);
+        let start = ((5 * 6 + 3) * 4) as usize;
+        image.data[start..start + 4].copy_from_slice(&[0, 255, 0, 255]);
+        assert_eq!(
+            NinePatchBuilder::from_android_image(&image, ()).unwrap_err(),
+            NinePatchError::InvalidGuidePixel { x: 3, y: 5 }
+        );
+    }
+
+    #[test]
+    fn unreadable_image() {
+        let mut image = guide_image(4, 4, &[]);
+        image.texture_descriptor.format = TextureFormat::R32Float;
+        assert_eq!(
+            NinePatchBuilder::from_android_image(&image, ()).unwrap_err(),
+            NinePatchError::UnreadableImage
+        );
+    }
+
+    #[test]
+    fn stretch_guides() {
+        // 6x6 texture with a stretchable 2x2 center, and the same content area
+        let marks = [
+            (3, 0),
+            (4, 0),
+            (0, 3),
+            (0, 4),
+            (3, 7),
+            (4, 7),
+            (7, 3),
+            (7, 4),
+        ];
+        let image = guide_image(8, 8, &marks);
+        let (texture, nine_patch) = NinePatchBuilder::from_android_image(&image, ()).unwrap();
+        assert_eq!(texture.texture_descriptor.size.width, 6);
+        assert_eq!(texture.texture_descriptor.size.height, 6);
+        assert!(texture
+            .data
+            .chunks(4)
+            .all(|pixel| pixel == [128, 128, 128, 255]));
+        assert_eq!(nine_patch.patches.len(), 3);
+        assert_eq!(
+            widths(&nine_patch),
+            vec![(2, Val::Px(0.)), (2, Val::Percent(100.)), (2, Val::Px(0.))]
+        );
+        assert_eq!(content_patch(&nine_patch), (1, 1));
+    }
+
+    #[test]
+    fn several_stretch_regions() {
+        // columns: 1 fixed, 1 stretchable, 2 fixed, 3 stretchable, 1 fixed
+        let marks = [(2, 0), (5, 0), (6, 0), (7, 0), (0, 3)];
+        let image = guide_image(10, 6, &marks);
+        let (_, nine_patch) = NinePatchBuilder::from_android_image(&image, ()).unwrap();
+        assert_eq!(
+            widths(&nine_patch),
+            vec![
+                (1, Val::Px(0.)),
+                (1, Val::Percent(25.)),
+                (2, Val::Px(0.)),
+                (3, Val::Percent(75.)),
+                (1, Val::Px(0.)),
+            ]
+        );
+        // without padding guides, the content area is the stretchable area
+        assert_eq!(content_patch(&nine_patch), (1, 1));
+    }
+
+    #[test]
+    fn content_inside_stretch_area() {
+        // stretchable columns 1 to 4, content columns 2 and 3
+        let marks = [(2, 0), (3, 0), (4, 0), (5, 0), (3, 5), (4, 5), (0, 2)];
+        let image = guide_image(8, 6, &marks);
+        let (_, nine_patch) = NinePatchBuilder::from_android_image(&image, ()).unwrap();
+        assert_eq!(
+            widths(&nine_patch),
+            vec![
+                (1, Val::Px(0.)),
+                (1, Val::Percent(25.)),
+                (2, Val::Percent(50.)),
+                (1, Val::Percent(25.)),
+                (1, Val::Px(0.)),
+            ]
+        );
+        assert_eq!(content_patch(&nine_patch), (1, 2));
+    }
+
+    #[test]
+    fn content_past_stretch_area() {
+        // stretchable columns 2 and 3, content columns 1 to 4
+        let marks = [
+            (3, 0),
+            (4, 0),
+            (2, 7),
+            (3, 7),
+            (4, 7),
+            (5, 7),
+            (0, 3),
+            (7, 3),
+        ];
+        let image = guide_image(8, 8, &marks);
+        let (_, nine_patch) = NinePatchBuilder::from_android_image(&image, ()).unwrap();
+        assert_eq!(
+            widths(&nine_patch),
+            vec![
+                (1, Val::Px(0.)),
+                (1, Val::Px(0.)),
+                (2, Val::Percent(100.)),
+                (1, Val::Px(0.)),
+                (1, Val::Px(0.)),
+            ]
+        );
+        assert_eq!(content_patch(&nine_patch), (1, 2));
+    }
+}
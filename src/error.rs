@@ -0,0 +1,106 @@
+use bevy::{render::render_resource::TextureFormat, ui::Val};
+
+/// Errors that can happen when building a `NinePatch`
+///
+/// The `NinePatchPlugin` sends them as [`NinePatchErrorEvent`](crate::NinePatchErrorEvent) when a
+/// 9-Patch UI element can't be created.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NinePatchError {
+    /// The image is too small to contain the 1 pixel guide border of an Android 9-Patch image
+    ImageTooSmall {
+        /// Width of the image, in pixels
+        width: u32,
+        /// Height of the image, in pixels
+        height: u32,
+    },
+    /// A guide pixel of an Android 9-Patch image is not transparent, white, black or red
+    InvalidGuidePixel {
+        /// Horizontal position of the pixel in the image
+        x: u32,
+        /// Vertical position of the pixel in the image
+        y: u32,
+    },
+    /// The image could not be converted to RGBA to read its guides
+    UnreadableImage,
+    /// The texture is not available in `Assets<Image>`, or no texture was set
+    MissingTexture,
+    /// The texture could not be loaded by the `AssetServer`
+    TextureLoadFailed,
+    /// The `NinePatchBuilder` could not be loaded by the `AssetServer`
+    BuilderLoadFailed,
+    /// The texture format can't be split in patches, for example because it is compressed
+    UnsupportedTextureFormat(TextureFormat),
+    /// The target size of a patch is negative or not finite
+    UnsupportedTargetSize(Val),
+    /// A row of the `NinePatchBuilder` has no patches
+    EmptyRow {
+        /// Index of the row
+        row: usize,
+    },
+    /// The negative original size of a patch is larger than the texture
+    NegativePatchSize {
+        /// Index of the row of the patch
+        row: usize,
+        /// Index of the patch in its row
+        column: usize,
+    },
+    /// A patch doesn't have the same height as the first patch of its row
+    RowHeightMismatch {
+        /// Index of the row of the patch
+        row: usize,
+        /// Index of the patch in its row
+        column: usize,
+    },
+    /// A patch goes past the right or bottom edge of the texture
+    PatchOutOfBounds {
+        /// Index of the row of the patch
+        row: usize,
+        /// Index of the patch in its row
+        column: usize,
+    },
+}
+
+impl std::fmt::Display for NinePatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NinePatchError::ImageTooSmall { width, height } => write!(
+                f,
+                "image of size {width}x{height} is too small to be a 9-Patch image"
+            ),
+            NinePatchError::InvalidGuidePixel { x, y } => write!(
+                f,
+                "guide pixel at ({x}, {y}) should be transparent, white, black or red"
+            ),
+            NinePatchError::UnreadableImage => {
+                write!(f, "image could not be converted to RGBA to read its guides")
+            }
+            NinePatchError::MissingTexture => write!(f, "texture is not loaded"),
+            NinePatchError::TextureLoadFailed => write!(f, "texture failed to load"),
+            NinePatchError::BuilderLoadFailed => write!(f, "nine-patch builder failed to load"),
+            NinePatchError::UnsupportedTextureFormat(format) => {
+                write!(f, "texture format {format:?} is not supported")
+            }
+            NinePatchError::UnsupportedTargetSize(size) => {
+                write!(
+                    f,
+                    "patch target size {size:?} should be positive and finite"
+                )
+            }
+            NinePatchError::EmptyRow { row } => write!(f, "row {row} has no patches"),
+            NinePatchError::NegativePatchSize { row, column } => write!(
+                f,
+                "patch at row {row}, column {column} has a negative size in the texture"
+            ),
+            NinePatchError::RowHeightMismatch { row, column } => write!(
+                f,
+                "patch at row {row}, column {column} doesn't have the height of its row"
+            ),
+            NinePatchError::PatchOutOfBounds { row, column } => write!(
+                f,
+                "patch at row {row}, column {column} goes past the texture"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for NinePatchError {}
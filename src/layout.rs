@@ -0,0 +1,473 @@
+use bevy::{prelude::*, render::render_resource::Extent3d};
+
+use crate::{ninepatch::*, NinePatchError};
+
+/// Part of the texture used by a patch, and where it is drawn in the nine-patch
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PatchRect {
+    /// Rectangle of the patch in the texture, in pixels
+    pub source: Rect,
+    /// Rectangle of the patch in the nine-patch, from its top left corner
+    pub target: Rect,
+}
+
+/// Check that a patch target size is positive and finite
+pub(crate) fn check_target_size(target: Val) -> Result<(), NinePatchError> {
+    let value = match target {
+        Val::Auto => 0.,
+        Val::Px(value)
+        | Val::Percent(value)
+        | Val::Vw(value)
+        | Val::Vh(value)
+        | Val::VMin(value)
+        | Val::VMax(value) => value,
+    };
+    if value >= 0. && value.is_finite() {
+        Ok(())
+    } else {
+        Err(NinePatchError::UnsupportedTargetSize(target))
+    }
+}
+
+/// How a patch is sized along one axis
+enum AxisSize {
+    /// The patch keeps this size
+    Fixed(f32),
+    /// The patch starts from `basis` and grows or shrinks with the available space
+    Flex { basis: f32, factor: f32 },
+}
+
+impl AxisSize {
+    fn new(target: Val, original: f32, viewport: Vec2) -> Result<Self, NinePatchError> {
+        check_target_size(target)?;
+        Ok(match target {
+            Val::Px(0.) => AxisSize::Fixed(original),
+            Val::Px(value) => AxisSize::Fixed(value),
+            Val::Vw(value) => AxisSize::Fixed(viewport.x * value / 100.),
+            Val::Vh(value) => AxisSize::Fixed(viewport.y * value / 100.),
+            Val::VMin(value) => AxisSize::Fixed(viewport.min_element() * value / 100.),
+            Val::VMax(value) => AxisSize::Fixed(viewport.max_element() * value / 100.),
+            Val::Auto => AxisSize::Flex {
+                basis: original,
+                factor: 1.,
+            },
+            Val::Percent(percent) => AxisSize::Flex {
+                basis: original,
+                factor: percent / 100.,
+            },
+        })
+    }
+
+    fn basis(&self) -> f32 {
+        match self {
+            AxisSize::Fixed(size) => *size,
+            AxisSize::Flex { basis, .. } => *basis,
+        }
+    }
+}
+
+/// Share `total` between patches along one axis, the same way the flexbox layout of the other
+/// render modes does: growing patches share the free space by their factor, and shrink relatively
+/// to their factor and original size when there is not enough space
+fn distribute(sizes: &[AxisSize], total: f32) -> Vec<f32> {
+    let free = total - sizes.iter().map(AxisSize::basis).sum::<f32>();
+    let factors: f32 = sizes
+        .iter()
+        .map(|size| match size {
+            AxisSize::Fixed(_) => 0.,
+            AxisSize::Flex { factor, .. } if free >= 0. => *factor,
+            AxisSize::Flex { factor, basis } => factor * basis,
+        })
+        .sum();
+    sizes
+        .iter()
+        .map(|size| match size {
+            AxisSize::Fixed(size) => *size,
+            AxisSize::Flex { basis, .. } if factors <= 0. => *basis,
+            AxisSize::Flex { basis, factor } if free >= 0. => {
+                basis + free * factor / factors.max(1.)
+            }
+            AxisSize::Flex { basis, factor } => (basis + free * factor * basis / factors).max(0.),
+        })
+        .collect()
+}
+
+/// Source and target rectangle of each patch, when a nine-patch with a texture of `texture_size` is
+/// displayed with `size`. Viewport units are relative to `viewport_size`, in the unit of `size`
+pub(crate) fn layout<T: Clone + Send + Sync + 'static>(
+    patches: &[Vec<Patch<T>>],
+    texture_size: Extent3d,
+    size: Vec2,
+    viewport_size: Vec2,
+) -> Result<Vec<Vec<PatchRect>>, NinePatchError> {
+    let row_sizes = patches
+        .iter()
+        .map(|row| match row.first() {
+            Some(patch) => AxisSize::new(
+                patch.target_size.height,
+                to_height(patch.original_size, texture_size) as f32,
+                viewport_size,
+            ),
+            None => Ok(AxisSize::Fixed(0.)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let row_heights = distribute(&row_sizes, size.y);
+
+    let mut rects = Vec::with_capacity(patches.len());
+    let (mut source_y, mut target_y) = (0., 0.);
+    for (row, target_height) in patches.iter().zip(row_heights) {
+        let source_height = row
+            .first()
+            .map(|patch| to_height(patch.original_size, texture_size) as f32)
+            .unwrap_or(0.);
+        let column_sizes = row
+            .iter()
+            .map(|patch| {
+                AxisSize::new(
+                    patch.target_size.width,
+                    to_width(patch.original_size, texture_size) as f32,
+                    viewport_size,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let column_widths = distribute(&column_sizes, size.x);
+
+        let (mut source_x, mut target_x) = (0., 0.);
+        let mut row_rects = Vec::with_capacity(row.len());
+        for (patch, target_width) in row.iter().zip(column_widths) {
+            let source_width = to_width(patch.original_size, texture_size) as f32;
+            row_rects.push(PatchRect {
+                source: Rect::new(
+                    source_x,
+                    source_y,
+                    source_x + source_width,
+                    source_y + source_height,
+                ),
+                target: Rect::new(
+                    target_x,
+                    target_y,
+                    target_x + target_width,
+                    target_y + target_height,
+                ),
+            });
+            source_x += source_width;
+            target_x += target_width;
+        }
+        rects.push(row_rects);
+        source_y += source_height;
+        target_y += target_height;
+    }
+    Ok(rects)
+}
+
+/// A repetition of a patch in its target rectangle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Tile {
+    /// Rectangle of the repetition, from the top left corner of the patch. It can go past the
+    /// patch, and should then be cut
+    pub(crate) target: Rect,
+    /// Is the repetition flipped horizontally
+    pub(crate) flip_x: bool,
+    /// Is the repetition flipped vertically
+    pub(crate) flip_y: bool,
+}
+
+/// Start, length and flip of each repetition of a patch of `source` length along one axis
+fn axis_tiles(mode: StretchMode, source: f32, target: f32) -> Vec<(f32, f32, bool)> {
+    if target <= 0. {
+        return vec![];
+    }
+    if source <= 0. {
+        return vec![(0., target, false)];
+    }
+    let (count, length) = match mode {
+        StretchMode::Stretch => (1, target),
+        StretchMode::Tile | StretchMode::Mirror => ((target / source).ceil() as usize, source),
+        StretchMode::TileFit => {
+            let count = (target / source).round().max(1.);
+            (count as usize, target / count)
+        }
+    };
+    (0..count)
+        .map(|index| {
+            (
+                index as f32 * length,
+                length,
+                mode == StretchMode::Mirror && index % 2 == 1,
+            )
+        })
+        .collect()
+}
+
+/// Repetitions of a patch of `source_size` drawn with `target_size`
+pub(crate) fn tiles(stretch: PatchStretch, source_size: Vec2, target_size: Vec2) -> Vec<Tile> {
+    let columns = axis_tiles(stretch.horizontal, source_size.x, target_size.x);
+    let rows = axis_tiles(stretch.vertical, source_size.y, target_size.y);
+    rows.iter()
+        .flat_map(|&(y, height, flip_y)| {
+            columns.iter().map(move |&(x, width, flip_x)| Tile {
+                target: Rect::new(x, y, x + width, y + height),
+                flip_x,
+                flip_y,
+            })
+        })
+        .collect()
+}
+
+/// Part of the texture drawn on a part of a nine-patch
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SliceQuad {
+    /// Index of the patch drawn
+    pub(crate) patch: usize,
+    /// Rectangle in the texture, in pixels
+    pub(crate) source: Rect,
+    /// Rectangle in the nine-patch, from its top left corner
+    pub(crate) target: Rect,
+    /// Is the texture flipped horizontally
+    pub(crate) flip_x: bool,
+    /// Is the texture flipped vertically
+    pub(crate) flip_y: bool,
+}
+
+/// Quads drawing a patch, cutting the repetitions that go past the patch
+pub(crate) fn patch_quads(
+    patch: usize,
+    stretch: PatchStretch,
+    rect: PatchRect,
+) -> impl Iterator<Item = SliceQuad> {
+    tiles(stretch, rect.source.size(), rect.target.size())
+        .into_iter()
+        .filter_map(move |tile| {
+            let tile_target = Rect {
+                min: rect.target.min + tile.target.min,
+                max: rect.target.min + tile.target.max,
+            };
+            let visible = tile_target.intersect(rect.target);
+            if visible.is_empty() {
+                return None;
+            }
+            // visible part of the repetition, from 0 to 1 in the patch texture
+            let mut start = (visible.min - tile_target.min) / tile_target.size();
+            let mut end = (visible.max - tile_target.min) / tile_target.size();
+            if tile.flip_x {
+                (start.x, end.x) = (1. - end.x, 1. - start.x);
+            }
+            if tile.flip_y {
+                (start.y, end.y) = (1. - end.y, 1. - start.y);
+            }
+            Some(SliceQuad {
+                patch,
+                source: Rect {
+                    min: rect.source.min + start * rect.source.size(),
+                    max: rect.source.min + end * rect.source.size(),
+                },
+                target: visible,
+                flip_x: tile.flip_x,
+                flip_y: tile.flip_y,
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patch(width: i32, height: i32, target_width: Val, target_height: Val) -> Patch<()> {
+        Patch {
+            original_size: IVec2::new(width, height),
+            target_size: Size::new(target_width, target_height),
+            content: None,
+            stretch: PatchStretch::default(),
+        }
+    }
+
+    fn texture_size(width: u32, height: u32) -> Extent3d {
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        }
+    }
+
+    fn widths(rects: &[Vec<PatchRect>]) -> Vec<f32> {
+        rects[0].iter().map(|rect| rect.target.width()).collect()
+    }
+
+    #[test]
+    fn grow() {
+        let patches = vec![vec![
+            patch(10, 10, Val::Px(0.), Val::Px(0.)),
+            patch(10, 10, Val::Auto, Val::Px(0.)),
+            patch(10, 10, Val::Percent(300.), Val::Px(0.)),
+            patch(10, 10, Val::Px(5.), Val::Px(0.)),
+        ]];
+        let rects = layout(
+            &patches,
+            texture_size(35, 10),
+            Vec2::new(75., 10.),
+            Vec2::ZERO,
+        )
+        .unwrap();
+        // 40 pixels of free space, shared 1 to 3
+        assert_eq!(widths(&rects), vec![10., 20., 40., 5.]);
+        assert_eq!(rects[0][2].source, Rect::new(20., 0., 30., 10.));
+        assert_eq!(rects[0][2].target, Rect::new(30., 0., 70., 10.));
+    }
+
+    #[test]
+    fn grow_with_small_factors() {
+        // factors adding up to less than 1 only take their share of the free space
+        let patches = vec![vec![
+            patch(10, 10, Val::Percent(25.), Val::Px(0.)),
+            patch(10, 10, Val::Percent(25.), Val::Px(0.)),
+        ]];
+        let rects = layout(
+            &patches,
+            texture_size(20, 10),
+            Vec2::new(60., 10.),
+            Vec2::ZERO,
+        )
+        .unwrap();
+        assert_eq!(widths(&rects), vec![20., 20.]);
+    }
+
+    #[test]
+    fn shrink() {
+        let patches = vec![vec![
+            patch(10, 10, Val::Px(0.), Val::Px(0.)),
+            patch(20, 10, Val::Auto, Val::Px(0.)),
+            patch(10, 10, Val::Auto, Val::Px(0.)),
+            patch(10, 10, Val::Px(0.), Val::Px(0.)),
+        ]];
+        let rects = layout(
+            &patches,
+            texture_size(50, 10),
+            Vec2::new(35., 10.),
+            Vec2::ZERO,
+        )
+        .unwrap();
+        // 15 missing pixels, taken relatively to the original size
+        assert_eq!(widths(&rects), vec![10., 10., 5., 10.]);
+        // flexible patches don't shrink below 0, fixed patches keep their size
+        let rects = layout(
+            &patches,
+            texture_size(50, 10),
+            Vec2::new(5., 10.),
+            Vec2::ZERO,
+        )
+        .unwrap();
+        assert_eq!(widths(&rects), vec![10., 0., 0., 10.]);
+    }
+
+    #[test]
+    fn rows() {
+        let patches = vec![
+            vec![patch(10, 5, Val::Px(0.), Val::Px(0.))],
+            vec![patch(10, 5, Val::Px(0.), Val::Auto)],
+            vec![patch(10, -10, Val::Px(0.), Val::Px(0.))],
+        ];
+        let rects = layout(
+            &patches,
+            texture_size(10, 20),
+            Vec2::new(10., 50.),
+            Vec2::ZERO,
+        )
+        .unwrap();
+        let heights: Vec<f32> = rects.iter().map(|row| row[0].target.height()).collect();
+        assert_eq!(heights, vec![5., 35., 10.]);
+        assert_eq!(rects[2][0].source, Rect::new(0., 10., 10., 20.));
+        assert_eq!(rects[2][0].target, Rect::new(0., 40., 10., 50.));
+    }
+
+    #[test]
+    fn viewport_units() {
+        let patches = vec![vec![
+            patch(10, 10, Val::Vw(10.), Val::Vh(10.)),
+            patch(10, 10, Val::VMin(10.), Val::Px(0.)),
+            patch(10, 10, Val::VMax(10.), Val::Px(0.)),
+        ]];
+        let rects = layout(
+            &patches,
+            texture_size(30, 10),
+            Vec2::new(100., 100.),
+            Vec2::new(800., 600.),
+        )
+        .unwrap();
+        assert_eq!(widths(&rects), vec![80., 60., 80.]);
+        assert_eq!(rects[0][0].target.height(), 60.);
+    }
+
+    #[test]
+    fn unsupported_target_size() {
+        let patches = vec![vec![patch(10, 10, Val::Px(-1.), Val::Px(0.))]];
+        assert_eq!(
+            layout(&patches, texture_size(10, 10), Vec2::ONE, Vec2::ONE),
+            Err(NinePatchError::UnsupportedTargetSize(Val::Px(-1.)))
+        );
+    }
+
+    #[test]
+    fn tile_modes() {
+        let stretch = |mode| PatchStretch::new(mode, StretchMode::Stretch);
+        let starts = |tiles: Vec<Tile>| -> Vec<(f32, f32, bool)> {
+            tiles
+                .iter()
+                .map(|tile| (tile.target.min.x, tile.target.max.x, tile.flip_x))
+                .collect()
+        };
+        let size = (Vec2::new(10., 10.), Vec2::new(25., 10.));
+        assert_eq!(
+            starts(tiles(stretch(StretchMode::Stretch), size.0, size.1)),
+            vec![(0., 25., false)]
+        );
+        assert_eq!(
+            starts(tiles(stretch(StretchMode::Tile), size.0, size.1)),
+            vec![(0., 10., false), (10., 20., false), (20., 30., false)]
+        );
+        assert_eq!(
+            starts(tiles(stretch(StretchMode::Mirror), size.0, size.1)),
+            vec![(0., 10., false), (10., 20., true), (20., 30., false)]
+        );
+        assert_eq!(
+            starts(tiles(
+                stretch(StretchMode::TileFit),
+                size.0,
+                Vec2::new(24., 10.)
+            )),
+            vec![(0., 12., false), (12., 24., false)]
+        );
+    }
+
+    #[test]
+    fn quads_cut_last_tile() {
+        let rect = PatchRect {
+            source: Rect::new(10., 0., 20., 10.),
+            target: Rect::new(5., 0., 30., 10.),
+        };
+        let quads: Vec<SliceQuad> = patch_quads(
+            3,
+            PatchStretch::new(StretchMode::Mirror, StretchMode::Stretch),
+            rect,
+        )
+        .collect();
+        assert_eq!(quads.len(), 3);
+        assert!(quads.iter().all(|quad| quad.patch == 3));
+        assert_eq!(quads[1].target, Rect::new(15., 0., 25., 10.));
+        assert!(quads[1].flip_x);
+        // the last repetition is cut to 5 pixels of the start of the texture
+        assert_eq!(quads[2].target, Rect::new(25., 0., 30., 10.));
+        assert_eq!(quads[2].source, Rect::new(10., 0., 15., 10.));
+        // a cut mirrored repetition keeps the end of the texture
+        let quads: Vec<SliceQuad> = patch_quads(
+            0,
+            PatchStretch::new(StretchMode::Mirror, StretchMode::Stretch),
+            PatchRect {
+                target: Rect::new(5., 0., 20., 10.),
+                ..rect
+            },
+        )
+        .collect();
+        assert_eq!(quads[1].target, Rect::new(15., 0., 20., 10.));
+        assert_eq!(quads[1].source, Rect::new(15., 0., 20., 10.));
+    }
+}
@@ -11,7 +11,44 @@
 #![doc = include_str!("../README.md")]
 
 mod ninepatch;
-pub use ninepatch::{NinePatch, NinePatchBuilder, NinePatchContent, Patch, Size};
+pub use ninepatch::{
+    NinePatch, NinePatchBuilder, NinePatchContent, NinePatchPart, NinePatchParts,
+    NinePatchRenderMode, NinePatchRow, NinePatchZones, Patch, PatchStretch, Size, StretchMode,
+};
 
 mod plugin;
 pub use plugin::*;
+
+mod states;
+pub use states::{NinePatchSkin, NinePatchStates};
+
+mod sprite;
+pub use sprite::{NinePatchSprite, NinePatchSpriteBundle};
+
+mod zones;
+pub use zones::BuildZoneChildren;
+
+mod error;
+pub use error::NinePatchError;
+
+mod layout;
+pub use layout::PatchRect;
+mod mesh;
+mod rasterize;
+pub use rasterize::NinePatchSampling;
+mod single_node;
+#[cfg(test)]
+mod test_utils;
+mod tiles;
+mod tint;
+
+mod android;
+pub use android::{AndroidNinePatchLoader, ANDROID_TEXTURE_LABEL};
+
+#[cfg(feature = "serde")]
+mod serialization;
+
+#[cfg(feature = "ron")]
+mod ron_loader;
+#[cfg(feature = "ron")]
+pub use ron_loader::NinePatchRonLoader;
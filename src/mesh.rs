@@ -0,0 +1,190 @@
+use bevy::{
+    prelude::*,
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+};
+
+use crate::{layout::*, ninepatch::NinePatch};
+
+impl<T: Clone + Send + Sync + Eq + std::hash::Hash + 'static> NinePatch<T> {
+    /// Build a mesh of `size` drawing the nine-patch with its original texture, centered on the
+    /// origin and facing `+Z`. Patches are sized as in a 9-Patch UI element, with viewport units
+    /// relative to `viewport_size`, and a quad is added for each repetition of a tiled patch.
+    ///
+    /// The mesh has positions, normals and UVs into the original texture, which can be used with
+    /// a `StandardMaterial` or a `ColorMaterial`.
+    ///
+    /// ```rust
+    /// # use bevy::prelude::*;
+    /// # use bevy_ninepatch::*;
+    /// fn panel_mesh(
+    ///     texture: &Handle<Image>,
+    ///     textures: &Assets<Image>,
+    ///     meshes: &mut Assets<Mesh>,
+    /// ) -> Result<Handle<Mesh>, NinePatchError> {
+    ///     let nine_patch = NinePatchBuilder::<()>::by_margins(20, 20, 20, 20)
+    ///         .apply_single_node(texture, textures)?;
+    ///     Ok(meshes.add(nine_patch.to_mesh(Vec2::new(4., 2.), Vec2::new(16., 9.))))
+    /// }
+    /// ```
+    pub fn to_mesh(&self, size: Vec2, viewport_size: Vec2) -> Mesh {
+        let texture_size = Vec2::new(
+            self.texture_size.width as f32,
+            self.texture_size.height as f32,
+        );
+        // target sizes have been checked when applying the `NinePatchBuilder`
+        let rects =
+            layout(&self.patches, self.texture_size, size, viewport_size).unwrap_or_default();
+        let quads = self
+            .patches
+            .iter()
+            .flatten()
+            .zip(rects.into_iter().flatten())
+            .enumerate()
+            .flat_map(|(index, (patch, rect))| patch_quads(index, patch.stretch, rect));
+
+        let mut positions = vec![];
+        let mut uvs = vec![];
+        let mut indices = vec![];
+        for quad in quads {
+            if quad.source.is_empty() || quad.target.is_empty() {
+                continue;
+            }
+            let (mut uv_min, mut uv_max) = (
+                quad.source.min / texture_size,
+                quad.source.max / texture_size,
+            );
+            if quad.flip_x {
+                std::mem::swap(&mut uv_min.x, &mut uv_max.x);
+            }
+            if quad.flip_y {
+                std::mem::swap(&mut uv_min.y, &mut uv_max.y);
+            }
+            // quads are placed from the top left corner with y going down, the mesh has y going up
+            let (left, right) = (
+                quad.target.min.x - size.x / 2.,
+                quad.target.max.x - size.x / 2.,
+            );
+            let (top, bottom) = (
+                size.y / 2. - quad.target.min.y,
+                size.y / 2. - quad.target.max.y,
+            );
+            let start = positions.len() as u32;
+            positions.extend([
+                [left, bottom, 0.],
+                [right, bottom, 0.],
+                [right, top, 0.],
+                [left, top, 0.],
+            ]);
+            uvs.extend([
+                [uv_min.x, uv_max.y],
+                [uv_max.x, uv_max.y],
+                [uv_max.x, uv_min.y],
+                [uv_min.x, uv_min.y],
+            ]);
+            indices.extend([start, start + 1, start + 2, start, start + 2, start + 3]);
+        }
+
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0., 0., 1.]; positions.len()]);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+        mesh.set_indices(Some(Indices::U32(indices)));
+        mesh
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils::*, NinePatchBuilder, Patch, PatchStretch, Size, StretchMode};
+    use bevy::render::{mesh::VertexAttributeValues, render_resource::TextureFormat};
+
+    /// Positions and UVs of the mesh drawing `nine_patch` from a 3x3 texture with `size`
+    fn mesh(nine_patch: NinePatchBuilder<()>, size: Vec2) -> (Vec<[f32; 3]>, Vec<[f32; 2]>) {
+        let mut app = app();
+        let mut textures = app.world.resource_mut::<Assets<Image>>();
+        let texture = textures.add(texture(3, 3, vec![0; 9], TextureFormat::R8Unorm));
+        let mesh = nine_patch
+            .apply_single_node(&texture, &textures)
+            .unwrap()
+            .to_mesh(size, Vec2::ZERO);
+        let Some(VertexAttributeValues::Float32x3(positions)) =
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            panic!("mesh should have positions");
+        };
+        let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0)
+        else {
+            panic!("mesh should have UVs");
+        };
+        (positions.clone(), uvs.clone())
+    }
+
+    /// Positions of a quad, in the order of the mesh
+    fn quad_positions(left: f32, right: f32, top: f32, bottom: f32) -> [[f32; 3]; 4] {
+        [
+            [left, bottom, 0.],
+            [right, bottom, 0.],
+            [right, top, 0.],
+            [left, top, 0.],
+        ]
+    }
+
+    /// UVs of a quad, in the order of the mesh
+    fn quad_uvs(left: f32, right: f32, top: f32, bottom: f32) -> [[f32; 2]; 4] {
+        [[left, bottom], [right, bottom], [right, top], [left, top]]
+    }
+
+    #[test]
+    fn margins() {
+        let (positions, uvs) = mesh(NinePatchBuilder::by_margins(1, 1, 1, 1), Vec2::new(5., 4.));
+        // left and right of each column, top and bottom of each row, in the mesh and the texture
+        let columns = [
+            (-2.5, -1.5, 0., 1.),
+            (-1.5, 1.5, 1., 2.),
+            (1.5, 2.5, 2., 3.),
+        ];
+        let rows = [(2., 1., 0., 1.), (1., -1., 1., 2.), (-1., -2., 2., 3.)];
+        let mut expected_positions = vec![];
+        let mut expected_uvs = vec![];
+        for (top, bottom, source_top, source_bottom) in rows {
+            for (left, right, source_left, source_right) in columns {
+                expected_positions.extend(quad_positions(left, right, top, bottom));
+                expected_uvs.extend(quad_uvs(
+                    source_left / 3.,
+                    source_right / 3.,
+                    source_top / 3.,
+                    source_bottom / 3.,
+                ));
+            }
+        }
+        assert_eq!(positions, expected_positions);
+        assert_eq!(uvs, expected_uvs);
+    }
+
+    #[test]
+    fn tiled_patch() {
+        let nine_patch = NinePatchBuilder::from_patches(vec![vec![Patch {
+            original_size: IVec2::new(2, 3),
+            target_size: Size::new(Val::Auto, Val::Auto),
+            content: None,
+            stretch: PatchStretch::new(StretchMode::Tile, StretchMode::Stretch),
+        }]]);
+        let (positions, uvs) = mesh(nine_patch, Vec2::new(5., 2.));
+        // a quad for each repetition, the last one showing half of the patch
+        let expected_positions: Vec<[f32; 3]> = [
+            quad_positions(-2.5, -0.5, 1., -1.),
+            quad_positions(-0.5, 1.5, 1., -1.),
+            quad_positions(1.5, 2.5, 1., -1.),
+        ]
+        .concat();
+        let expected_uvs: Vec<[f32; 2]> = [
+            quad_uvs(0., 2. / 3., 0., 1.),
+            quad_uvs(0., 2. / 3., 0., 1.),
+            quad_uvs(0., 1. / 3., 0., 1.),
+        ]
+        .concat();
+        assert_eq!(positions, expected_positions);
+        assert_eq!(uvs, expected_uvs);
+    }
+}
@@ -1,12 +1,22 @@
 use bevy::{
     prelude::*,
-    reflect::{TypeUuid, TypePath},
-    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+    reflect::{TypePath, TypeUuid},
+    render::render_resource::{Extent3d, TextureDimension},
     ui::FocusPolicy,
 };
 
+use crate::{
+    layout::{check_target_size, PatchRect},
+    tiles::{PatchTiles, TileTexture},
+    NinePatchError,
+};
+
 /// Represents Size of an element with width and height.
-#[derive(Debug, Clone, Copy)]
+///
+/// With the `serde` feature, it is serialized as a struct with fields `width` and `height`, using
+/// the representation of `Val` from Bevy.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(missing_docs)]
 pub struct Size {
     pub width: Val,
@@ -20,27 +30,110 @@ impl Size {
     }
 }
 
+/// How the patches of a `NinePatch` are rendered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NinePatchRenderMode {
+    /// Each patch is copied to its own texture
+    #[default]
+    SplitTextures,
+    /// Patches are drawn from the original texture through a `TextureAtlas`, without creating new
+    /// textures. Patch rectangles are inset by half a texel so that they aren't blended with their
+    /// neighbours
+    TextureAtlas,
+    /// The whole nine-patch is drawn by a single UI node, slicing the original texture when
+    /// rendering. Content zones are laid out by invisible nodes
+    SingleNode,
+}
+
+/// How a patch fills its target size along an axis, like the axis stretch modes of Godot's
+/// `NinePatchRect`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StretchMode {
+    /// The patch is scaled to its target size
+    #[default]
+    Stretch,
+    /// The patch is repeated at its original size, the last repetition is cut
+    Tile,
+    /// The patch is repeated, and scaled so that a whole number of repetitions fit
+    TileFit,
+    /// The patch is repeated at its original size, flipping every other repetition
+    Mirror,
+}
+
+/// Stretch modes of a patch on each axis
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PatchStretch {
+    /// How the patch fills its target width
+    pub horizontal: StretchMode,
+    /// How the patch fills its target height
+    pub vertical: StretchMode,
+}
+
+impl PatchStretch {
+    /// Creates a new `PatchStretch` with the given horizontal and vertical modes
+    pub fn new(horizontal: StretchMode, vertical: StretchMode) -> Self {
+        PatchStretch {
+            horizontal,
+            vertical,
+        }
+    }
+
+    /// Creates a new `PatchStretch` with the same mode on both axis
+    pub fn all(mode: StretchMode) -> Self {
+        Self::new(mode, mode)
+    }
+}
+
 /// Describe a patch in the original image, how it should grow and if it can have content
-#[derive(Debug, Clone)]
+///
+/// With the `serde` feature, it is serialized as a struct with fields `original_size` as a tuple
+/// `(x, y)`, `target_size` as a [`Size`], `content` as an `Option<T>` and `stretch` as a
+/// [`PatchStretch`]. `content` and `stretch` can be omitted.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "T: serde::Serialize",
+        deserialize = "T: serde::Deserialize<'de>"
+    ))
+)]
 pub struct Patch<T: Clone + Send + Sync + 'static> {
     /// Size of the patch in the original image
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::ivec2"))]
     pub original_size: IVec2,
     /// Size of the patch rendered
     pub target_size: Size,
     /// Does this patch can contain content
+    #[cfg_attr(feature = "serde", serde(default))]
     pub content: Option<T>,
+    /// How the patch fills its target size
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub stretch: PatchStretch,
 }
 
 /// Holds the patches of a nine patch texture
-#[derive(Debug,TypePath)]
-pub struct NinePatchBuilder<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static = ()> {
+///
+/// With the `serde` feature, it is serialized as a struct with the fields `patches` and
+/// `render_mode` as an `Option<NinePatchRenderMode>`. `render_mode` can be omitted, and the texture
+/// is not serialized.
+#[derive(Debug, TypePath)]
+pub struct NinePatchBuilder<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static = ()>
+{
     /// Patches for a nine patch texture. See example `full.rs` on how to use directly
     pub patches: Vec<Vec<Patch<T>>>,
-    pub(crate) patch_textures: Option<Vec<Handle<Image>>>,
-    pub(crate) original_texture: Option<Handle<Image>>,
+    /// Texture used by `NinePatchData` that don't set their own texture
+    pub texture: Option<Handle<Image>>,
+    /// How patches are rendered. If not set, the mode from `NinePatchSettings` is used
+    pub render_mode: Option<NinePatchRenderMode>,
 }
 
-impl<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> TypeUuid for NinePatchBuilder<T> {
+impl<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> TypeUuid
+    for NinePatchBuilder<T>
+{
     const TYPE_UUID: bevy::reflect::Uuid =
         bevy::reflect::Uuid::from_u128(0xee097b8ab9a747e3ad5c09e4a9c9ccb0);
 }
@@ -50,13 +143,29 @@ impl<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> NinePat
     pub fn from_patches(patches: Vec<Vec<Patch<T>>>) -> Self {
         Self {
             patches,
-            patch_textures: None,
-            original_texture: None,
+            texture: None,
+            render_mode: None,
         }
     }
+
+    /// Set how all patches fill their target size
+    pub fn with_stretch(mut self, stretch: PatchStretch) -> Self {
+        for patch in self.patches.iter_mut().flatten() {
+            patch.stretch = stretch;
+        }
+        self
+    }
+
+    /// Set how patches are rendered for this `NinePatchBuilder`
+    pub fn with_render_mode(mut self, render_mode: NinePatchRenderMode) -> Self {
+        self.render_mode = Some(render_mode);
+        self
+    }
 }
 
-impl<T: Clone + Send + Sync + Default + Eq + std::hash::Hash + TypePath + 'static> NinePatchBuilder<T> {
+impl<T: Clone + Send + Sync + Default + Eq + std::hash::Hash + TypePath + 'static>
+    NinePatchBuilder<T>
+{
     /// Create a simple nine patch split by creating fixed patch for the margins, and growing patches inside
     pub fn by_margins(
         top_margin: u32,
@@ -88,6 +197,7 @@ impl<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> NinePat
                 original_size: IVec2::new(left_margin as i32, top_margin as i32),
                 target_size: Size::new(Val::Px(0.), Val::Px(0.)),
                 content: None,
+                stretch: PatchStretch::default(),
             },
             Patch {
                 original_size: IVec2::new(
@@ -96,11 +206,13 @@ impl<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> NinePat
                 ),
                 target_size: Size::new(Val::Auto, Val::Px(0.)),
                 content: None,
+                stretch: PatchStretch::default(),
             },
             Patch {
                 original_size: IVec2::new(right_margin as i32, top_margin as i32),
                 target_size: Size::new(Val::Px(0.), Val::Px(0.)),
                 content: None,
+                stretch: PatchStretch::default(),
             },
         ];
         let middle = vec![
@@ -111,6 +223,7 @@ impl<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> NinePat
                 ),
                 target_size: Size::new(Val::Px(0.), Val::Auto),
                 content: None,
+                stretch: PatchStretch::default(),
             },
             Patch {
                 original_size: IVec2::new(
@@ -119,6 +232,7 @@ impl<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> NinePat
                 ),
                 target_size: Size::new(Val::Auto, Val::Auto),
                 content: Some(content),
+                stretch: PatchStretch::default(),
             },
             Patch {
                 original_size: IVec2::new(
@@ -127,6 +241,7 @@ impl<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> NinePat
                 ),
                 target_size: Size::new(Val::Px(0.), Val::Auto),
                 content: None,
+                stretch: PatchStretch::default(),
             },
         ];
         let bottom = vec![
@@ -134,6 +249,7 @@ impl<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> NinePat
                 original_size: IVec2::new(left_margin as i32, bottom_margin as i32),
                 target_size: Size::new(Val::Px(0.), Val::Px(0.)),
                 content: None,
+                stretch: PatchStretch::default(),
             },
             Patch {
                 original_size: IVec2::new(
@@ -142,29 +258,31 @@ impl<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> NinePat
                 ),
                 target_size: Size::new(Val::Auto, Val::Px(0.)),
                 content: None,
+                stretch: PatchStretch::default(),
             },
             Patch {
                 original_size: IVec2::new(right_margin as i32, bottom_margin as i32),
                 target_size: Size::new(Val::Px(0.), Val::Px(0.)),
                 content: None,
+                stretch: PatchStretch::default(),
             },
         ];
         Self {
             patches: vec![top, middle, bottom],
-            patch_textures: None,
-            original_texture: None,
+            texture: None,
+            render_mode: None,
         }
     }
 }
 
-fn to_width(patch: IVec2, total: Extent3d) -> u32 {
+pub(crate) fn to_width(patch: IVec2, total: Extent3d) -> u32 {
     if patch.x > 0 {
         patch.x as u32
     } else {
         (total.width as i32 + patch.x) as u32
     }
 }
-fn to_height(patch: IVec2, total: Extent3d) -> u32 {
+pub(crate) fn to_height(patch: IVec2, total: Extent3d) -> u32 {
     if patch.y > 0 {
         patch.y as u32
     } else {
@@ -172,69 +290,223 @@ fn to_height(patch: IVec2, total: Extent3d) -> u32 {
     }
 }
 
+/// Size of a row or a patch node along one axis, and its flex grow and shrink factor, for a patch of
+/// `original` pixels with the `target` size
+pub(crate) fn flex_size(target: Val, original: u32) -> (Val, f32) {
+    match target {
+        Val::Px(0.) => (Val::Px(original as f32), 0.),
+        Val::Px(i) => (Val::Px(i), 0.),
+        Val::Auto => (Val::Auto, 1.),
+        Val::Percent(x) => (Val::Auto, x / 100.),
+        viewport @ (Val::Vw(_) | Val::Vh(_) | Val::VMin(_) | Val::VMax(_)) => (viewport, 0.),
+    }
+}
+
 impl<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> NinePatchBuilder<T> {
+    /// Check that the patches can be applied to a texture of the given size: all patches of a row
+    /// must have the same height, negative original sizes must not be larger than the texture, and
+    /// patches must not go past the texture. Target sizes must be positive and finite.
+    pub fn validate(&self, texture_size: UVec2) -> Result<(), NinePatchError> {
+        let resolve = |size: i32, total: u32| {
+            if size > 0 {
+                size as i64
+            } else {
+                total as i64 + size as i64
+            }
+        };
+        let mut accu_y = 0;
+        for (row, patches) in self.patches.iter().enumerate() {
+            let Some(first) = patches.first() else {
+                return Err(NinePatchError::EmptyRow { row });
+            };
+            let row_height = resolve(first.original_size.y, texture_size.y);
+            let mut accu_x = 0;
+            for (column, patch) in patches.iter().enumerate() {
+                let width = resolve(patch.original_size.x, texture_size.x);
+                let height = resolve(patch.original_size.y, texture_size.y);
+                if width < 0 || height < 0 {
+                    return Err(NinePatchError::NegativePatchSize { row, column });
+                }
+                if height != row_height {
+                    return Err(NinePatchError::RowHeightMismatch { row, column });
+                }
+                accu_x += width;
+                if accu_x > texture_size.x as i64 || accu_y + height > texture_size.y as i64 {
+                    return Err(NinePatchError::PatchOutOfBounds { row, column });
+                }
+                check_target_size(patch.target_size.width)?;
+                check_target_size(patch.target_size.height)?;
+            }
+            accu_y += row_height;
+        }
+        Ok(())
+    }
+
+    /// Source and target rectangle of each patch, by row, when the nine-patch is displayed with
+    /// `target_size` using a texture of `texture_size`. Patches are sized as in a 9-Patch UI
+    /// element, with viewport units relative to `viewport_size`. This doesn't need the texture or
+    /// an `App`, and can be used for custom rendering or hit-testing.
+    ///
+    /// ```rust
+    /// # use bevy::prelude::*;
+    /// # use bevy_ninepatch::*;
+    /// let nine_patch = NinePatchBuilder::<()>::by_margins(10, 10, 10, 10);
+    /// let rects = nine_patch
+    ///     .layout(UVec2::new(30, 30), Vec2::new(100., 50.), Vec2::new(800., 600.))
+    ///     .unwrap();
+    /// assert_eq!(rects[1][1].source, Rect::new(10., 10., 20., 20.));
+    /// assert_eq!(rects[1][1].target, Rect::new(10., 10., 90., 40.));
+    /// ```
+    pub fn layout(
+        &self,
+        texture_size: UVec2,
+        target_size: Vec2,
+        viewport_size: Vec2,
+    ) -> Result<Vec<Vec<PatchRect>>, NinePatchError> {
+        self.validate(texture_size)?;
+        let texture_size = Extent3d {
+            width: texture_size.x,
+            height: texture_size.y,
+            depth_or_array_layers: 1,
+        };
+        crate::layout::layout(&self.patches, texture_size, target_size, viewport_size)
+    }
+
+    /// Rectangle of each patch in a texture of the given size
+    fn patch_rects(&self, texture_size: Extent3d) -> Vec<Rect> {
+        let mut rects = vec![];
+        let mut accu_y = 0;
+        for row in &self.patches {
+            let mut accu_x = 0;
+            for column_item in row {
+                let width = to_width(column_item.original_size, texture_size);
+                let height = to_height(column_item.original_size, texture_size);
+                rects.push(Rect::new(
+                    accu_x as f32,
+                    accu_y as f32,
+                    (accu_x + width) as f32,
+                    (accu_y + height) as f32,
+                ));
+                accu_x += width;
+            }
+            accu_y += to_height(row[0].original_size, texture_size);
+        }
+        rects
+    }
+
     /// Apply a `NinePatchBuilder` to a texture to get a `NinePatch` ready to be added to entities. This will split
     /// the given texture according to the patches.
+    ///
+    /// Patch textures keep the format and sampler of the original texture. Compressed formats are not supported.
+    /// New textures are created on each call, the `NinePatchPlugin` keeps them for each texture.
     pub fn apply(
-        &mut self,
+        &self,
         texture_handle: &Handle<Image>,
         textures: &mut Assets<Image>,
-    ) -> NinePatch<T> {
-        let (texture_size, texture_data) = {
-            let t = textures
-                .get(texture_handle)
-                .expect("could not get texture from handle");
-            (t.texture_descriptor.size, &t.data)
+    ) -> Result<NinePatch<T>, NinePatchError> {
+        let texture = textures
+            .get(texture_handle)
+            .ok_or(NinePatchError::MissingTexture)?;
+        let texture_size = texture.texture_descriptor.size;
+        self.validate(UVec2::new(texture_size.width, texture_size.height))?;
+        let format = texture.texture_descriptor.format;
+        let pixel_size = match format.block_size(None) {
+            Some(block_size) if format.block_dimensions() == (1, 1) => block_size as usize,
+            _ => return Err(NinePatchError::UnsupportedTextureFormat(format)),
         };
         let mut textures_to_add = vec![];
-        if self.patch_textures.is_none() || self.original_texture.as_ref() != Some(texture_handle) {
-            let mut patch_textures = vec![];
-            let mut accu_y = 0;
-            for row in &self.patches {
-                let mut accu_x = 0;
-                for column_item in row {
-                    let start_x = accu_x;
-                    let end_x = accu_x + to_width(column_item.original_size, texture_size);
-
-                    let start_y = accu_y;
-                    let end_y = accu_y + to_height(column_item.original_size, texture_size);
-
-                    let mut patch_texture_data = vec![];
-                    for j in start_y as usize..end_y as usize {
-                        let start_line = (start_x as usize + j * texture_size.width as usize) * 4;
-                        let end_line = (end_x as usize + j * texture_size.width as usize) * 4;
-                        patch_texture_data.extend_from_slice(&texture_data[start_line..end_line]);
-                    }
-
-                    let patch_texture = Image::new(
-                        Extent3d {
-                            width: to_width(column_item.original_size, texture_size),
-                            height: to_height(column_item.original_size, texture_size),
-                            depth_or_array_layers: texture_size.depth_or_array_layers,
-                        },
-                        TextureDimension::D2,
-                        patch_texture_data,
-                        TextureFormat::Rgba8UnormSrgb,
-                    );
-                    textures_to_add.push(patch_texture);
-                    accu_x += to_width(column_item.original_size, texture_size);
-                }
-                accu_y += to_height(row[0].original_size, texture_size);
+        for rect in self.patch_rects(texture_size) {
+            let (start_x, end_x) = (rect.min.x as usize, rect.max.x as usize);
+            let mut patch_texture_data = vec![];
+            for j in rect.min.y as usize..rect.max.y as usize {
+                let start_line = (start_x + j * texture_size.width as usize) * pixel_size;
+                let end_line = (end_x + j * texture_size.width as usize) * pixel_size;
+                patch_texture_data.extend_from_slice(&texture.data[start_line..end_line]);
             }
-            textures_to_add.into_iter().for_each(|patch_texture| {
-                let patch_texture_handle = textures.add(patch_texture);
-                patch_textures.push(patch_texture_handle);
-            });
-            self.patch_textures = Some(patch_textures);
-            self.original_texture = Some(texture_handle.clone());
+
+            let mut patch_texture = Image::new(
+                Extent3d {
+                    width: rect.width() as u32,
+                    height: rect.height() as u32,
+                    depth_or_array_layers: 1,
+                },
+                TextureDimension::D2,
+                patch_texture_data,
+                format,
+            );
+            patch_texture.sampler_descriptor = texture.sampler_descriptor.clone();
+            textures_to_add.push(patch_texture);
         }
+        let patch_textures = textures_to_add
+            .into_iter()
+            .map(|patch_texture| textures.add(patch_texture))
+            .collect();
+        Ok(self.with_patch_textures(
+            texture_handle,
+            texture_size,
+            PatchTextures::Split(patch_textures),
+        ))
+    }
+
+    /// Apply a `NinePatchBuilder` to a texture to get a `NinePatch` ready to be added to entities. This will not
+    /// create new textures, but a `TextureAtlas` with a rectangle for each patch of the given texture.
+    ///
+    /// Rectangles are inset by half a texel, so that a linear sampler doesn't blend in the texels of the neighbouring
+    /// patches, as patches split in their own texture are clamped to their edge.
+    pub fn apply_atlas(
+        &self,
+        texture_handle: &Handle<Image>,
+        textures: &Assets<Image>,
+        atlases: &mut Assets<TextureAtlas>,
+    ) -> Result<NinePatch<T>, NinePatchError> {
+        let texture = textures
+            .get(texture_handle)
+            .ok_or(NinePatchError::MissingTexture)?;
+        let texture_size = texture.texture_descriptor.size;
+        self.validate(UVec2::new(texture_size.width, texture_size.height))?;
+        let mut atlas = TextureAtlas::new_empty(
+            texture_handle.clone(),
+            Vec2::new(texture_size.width as f32, texture_size.height as f32),
+        );
+        for rect in self.patch_rects(texture_size) {
+            atlas.add_texture(atlas_rect(rect));
+        }
+        Ok(self.with_patch_textures(
+            texture_handle,
+            texture_size,
+            PatchTextures::Atlas(atlases.add(atlas)),
+        ))
+    }
+
+    /// `NinePatch` using textures previously created for a texture of the given size
+    pub(crate) fn with_patch_textures(
+        &self,
+        texture: &Handle<Image>,
+        texture_size: Extent3d,
+        textures: PatchTextures,
+    ) -> NinePatch<T> {
         NinePatch {
             patches: self.patches.clone(),
+            texture: texture.clone_weak(),
             texture_size,
-            // background: materials.add(Color::NONE.into()),
-            splitted_texture: self.patch_textures.as_ref().cloned().unwrap(),
+            textures,
         }
     }
+
+    /// Apply a `NinePatchBuilder` to a texture to get a `NinePatch` ready to be added to entities. This will not
+    /// create new textures, the nine-patch is drawn by a single UI node slicing the given texture.
+    pub fn apply_single_node(
+        &self,
+        texture_handle: &Handle<Image>,
+        textures: &Assets<Image>,
+    ) -> Result<NinePatch<T>, NinePatchError> {
+        let texture = textures
+            .get(texture_handle)
+            .ok_or(NinePatchError::MissingTexture)?;
+        let texture_size = texture.texture_descriptor.size;
+        self.validate(UVec2::new(texture_size.width, texture_size.height))?;
+        Ok(self.with_patch_textures(texture_handle, texture_size, PatchTextures::Single))
+    }
 }
 
 /// Component to mark the entity placed for the content of the 9-Patch UI element
@@ -248,46 +520,228 @@ pub struct NinePatchContent<T: Send + Sync + 'static> {
     pub parent: Entity,
 }
 
+/// Marks the entities generated as children of the entity holding the `NinePatchData`, so that they
+/// can be removed even if that entity has been despawned
+#[derive(Debug, Clone, Copy, Component)]
+pub(crate) struct GeneratedNode {
+    /// Entity holding the `NinePatchData`
+    pub(crate) nine_patch: Entity,
+}
+
+/// Make `entity` a transparent UI node with `style`. Node components it already has are kept, so
+/// that rebuilding a nine-patch doesn't reset them
+pub(crate) fn insert_node(commands: &mut Commands, entity: Entity, style: Style) {
+    fn insert_missing<C: Component>(entity: &mut bevy::ecs::world::EntityMut, component: C) {
+        if !entity.contains::<C>() {
+            entity.insert(component);
+        }
+    }
+    commands
+        .entity(entity)
+        .add(move |entity: Entity, world: &mut World| {
+            let Some(mut entity) = world.get_entity_mut(entity) else {
+                return;
+            };
+            entity.insert(style);
+            insert_missing(&mut entity, Node::default());
+            insert_missing(&mut entity, BackgroundColor(Color::NONE));
+            insert_missing(&mut entity, BorderColor::default());
+            insert_missing(&mut entity, FocusPolicy::Pass);
+            insert_missing(&mut entity, Transform::default());
+            insert_missing(&mut entity, GlobalTransform::default());
+            insert_missing(&mut entity, Visibility::default());
+            insert_missing(&mut entity, ComputedVisibility::default());
+            insert_missing(&mut entity, ZIndex::default());
+        });
+}
+
+/// Component marking a row of patches generated for a 9-Patch UI element
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+pub struct NinePatchRow {
+    /// Index of the row in the patches of the `NinePatchBuilder`
+    pub row: usize,
+}
+
+/// Component marking a patch generated for a 9-Patch UI element, or a content zone when it is
+/// rendered as a single node
+#[derive(Debug, Clone, PartialEq, Eq, Component)]
+pub struct NinePatchPart<T: Clone + Send + Sync + 'static> {
+    /// Index of the row of the patch
+    pub row: usize,
+    /// Index of the patch in its row
+    pub column: usize,
+    /// Content of the patch, if it is a content zone
+    pub content: Option<T>,
+}
+
+impl<T: Clone + Send + Sync + 'static> NinePatchPart<T> {
+    /// Component and name for the patch `patch`, at `row` and `column`
+    pub(crate) fn for_patch(row: usize, column: usize, patch: &Patch<T>) -> (Self, Name) {
+        (
+            NinePatchPart {
+                row,
+                column,
+                content: patch.content.clone(),
+            },
+            Name::new(format!("Nine-patch part {row}:{column}")),
+        )
+    }
+}
+
+/// Content zone entities of a 9-Patch UI element by content, added on the entity holding the
+/// `NinePatchData` once it is displayed
+#[derive(Debug, Clone, Component)]
+pub struct NinePatchZones<T: Clone + Send + Sync + Eq + std::hash::Hash + 'static> {
+    pub(crate) zones: std::collections::HashMap<T, Entity>,
+}
+
+impl<T: Clone + Send + Sync + Eq + std::hash::Hash + 'static> NinePatchZones<T> {
+    /// Entity of the content zone for `content`
+    pub fn get(&self, content: &T) -> Option<Entity> {
+        self.zones.get(content).copied()
+    }
+
+    /// Iterate over the content zones and their entity
+    pub fn iter(&self) -> impl Iterator<Item = (&T, Entity)> {
+        self.zones.iter().map(|(content, zone)| (content, *zone))
+    }
+}
+
+/// Entities generated for a `NinePatch`, added on the entity holding the `NinePatchData` once it is
+/// displayed
+#[derive(Debug, Clone, Component)]
+pub struct NinePatchParts<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> {
+    /// Children of the `NinePatchData` entity: row entities, or the node laying out the content
+    /// zones for a single node
+    pub(crate) rows: Vec<Entity>,
+    /// Patch entities, in the same order as the patches of the `NinePatch`
+    pub(crate) patches: Vec<Entity>,
+    /// `NinePatchBuilder` used to generate the patches
+    pub(crate) nine_patch: Handle<NinePatchBuilder<T>>,
+    /// Texture used to generate the patches
+    pub(crate) texture: Handle<Image>,
+    /// How the patches are rendered
+    pub(crate) render_mode: NinePatchRenderMode,
+    /// Size of the texture used to generate the patches
+    pub(crate) texture_size: Extent3d,
+    /// Patches used to generate the entities
+    pub(crate) patches_layout: Vec<Vec<Patch<T>>>,
+    /// Entities from `NinePatchData::content` placed in the content zones
+    pub(crate) contents: std::collections::HashMap<T, Vec<Entity>>,
+}
+
+impl<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> NinePatchParts<T> {
+    /// Children of the entity holding the `NinePatchData`: row entities, or the node laying out the
+    /// content zones when rendered as a single node
+    pub fn rows(&self) -> &[Entity] {
+        &self.rows
+    }
+
+    /// Patch entities, row by row. Empty when rendered as a single node
+    pub fn patches(&self) -> &[Entity] {
+        &self.patches
+    }
+
+    /// Entity of the patch at `row` and `column`. `None` when rendered as a single node
+    pub fn patch(&self, row: usize, column: usize) -> Option<Entity> {
+        if column >= self.patches_layout.get(row)?.len() {
+            return None;
+        }
+        let index: usize = self.patches_layout[..row].iter().map(Vec::len).sum();
+        self.patches.get(index + column).copied()
+    }
+
+    /// How the patches are rendered
+    pub fn render_mode(&self) -> NinePatchRenderMode {
+        self.render_mode
+    }
+}
+
+/// Rectangle of a patch in the `TextureAtlas` of a texture, inset by half a texel. Linear sampling
+/// then stays in the texels of the patch
+fn atlas_rect(rect: Rect) -> Rect {
+    // empty patches can't be inset
+    let inset = rect.size().min(Vec2::ONE) / 2.;
+    Rect {
+        min: rect.min + inset,
+        max: rect.max - inset,
+    }
+}
+
 /// `NinePatch` ready to be added to entities.
 #[derive(Debug)]
 pub struct NinePatch<T: Clone + Send + Sync + Eq + std::hash::Hash + 'static> {
-    patches: Vec<Vec<Patch<T>>>,
-    texture_size: Extent3d,
+    pub(crate) patches: Vec<Vec<Patch<T>>>,
+    pub(crate) texture: Handle<Image>,
+    pub(crate) texture_size: Extent3d,
     // background: Handle<Image>,
-    splitted_texture: Vec<Handle<Image>>,
+    pub(crate) textures: PatchTextures,
+}
+
+/// Textures used to render the patches of a `NinePatch`
+#[derive(Debug, Clone)]
+pub(crate) enum PatchTextures {
+    /// One texture per patch
+    Split(Vec<Handle<Image>>),
+    /// One rectangle per patch in a `TextureAtlas`
+    Atlas(Handle<TextureAtlas>),
+    /// The original texture, sliced when rendering
+    Single,
 }
-impl<T: Clone + Send + Sync + Eq + std::hash::Hash + 'static> NinePatch<T> {
+
+impl PatchTextures {
+    /// Render mode using these textures
+    pub(crate) fn render_mode(&self) -> NinePatchRenderMode {
+        match self {
+            PatchTextures::Split(_) => NinePatchRenderMode::SplitTextures,
+            PatchTextures::Atlas(_) => NinePatchRenderMode::TextureAtlas,
+            PatchTextures::Single => NinePatchRenderMode::SingleNode,
+        }
+    }
+}
+impl<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> NinePatch<T> {
     pub(crate) fn add_with_parent(
         &self,
         commands: &mut Commands,
         parent: Entity,
         style: &Style,
-        contents: &Option<std::collections::HashMap<T, Entity>>,
-    ) {
-        commands.entity(parent).insert(NodeBundle {
-            style: Style {
+        contents: &Option<std::collections::HashMap<T, Vec<Entity>>>,
+        nine_patch: &Handle<NinePatchBuilder<T>>,
+        texture: &Handle<Image>,
+    ) -> NinePatchParts<T> {
+        let patch_textures: Vec<TileTexture> = match &self.textures {
+            PatchTextures::Split(textures) => {
+                textures.iter().cloned().map(TileTexture::Image).collect()
+            }
+            PatchTextures::Atlas(atlas) => (0..self.patches.iter().map(Vec::len).sum())
+                .map(|n| TileTexture::Atlas(atlas.clone(), n))
+                .collect(),
+            PatchTextures::Single => {
+                return self
+                    .add_single_node(commands, parent, style, contents, nine_patch, texture);
+            }
+        };
+        insert_node(
+            commands,
+            parent,
+            Style {
                 flex_direction: FlexDirection::Column,
                 align_content: AlignContent::Stretch,
                 ..style.clone()
             },
-            background_color: BackgroundColor(Color::NONE),
-            focus_policy: FocusPolicy::Pass,
-            ..Default::default()
-        });
+        );
         let mut rows = vec![];
+        let mut patches = vec![];
+        let mut zones = std::collections::HashMap::new();
         let mut n = 0;
         for (row_index, row) in self.patches.iter().enumerate() {
             let (size_height, growth) = row
-                .get(0)
-                .map(|p| match p.target_size.height {
-                    Val::Px(value) if value == 0. => (
-                        Val::Px(to_height(p.original_size, self.texture_size) as f32),
-                        0.,
-                    ),
-                    Val::Px(i) => (Val::Px(i), 0.),
-                    Val::Auto => (Val::Auto, 1.),
-                    Val::Percent(x) => (Val::Auto, x / 100.),
-                    _ => todo!(),
+                .first()
+                .map(|p| {
+                    flex_size(
+                        p.target_size.height,
+                        to_height(p.original_size, self.texture_size),
+                    )
                 })
                 .unwrap_or((Val::Px(0.), 0.));
 
@@ -308,64 +762,389 @@ impl<T: Clone + Send + Sync + Eq + std::hash::Hash + 'static> NinePatch<T> {
                     focus_policy: FocusPolicy::Pass,
                     ..Default::default()
                 })
+                .insert((
+                    NinePatchRow { row: row_index },
+                    Name::new(format!("Nine-patch row {row_index}")),
+                ))
                 .id();
             rows.push(id);
             commands.entity(id).with_children(|row_parent| {
                 for (column_index, column_item) in row.iter().enumerate() {
-                    let (size_width, growth) = match column_item.target_size.width {
-                        Val::Px(value) if value == 0. => (
-                            Val::Px(to_width(column_item.original_size, self.texture_size) as f32),
-                            0.,
-                        ),
-                        Val::Px(i) => (Val::Px(i), 0.),
-                        Val::Auto => (Val::Auto, 1.),
-                        Val::Percent(x) => (Val::Auto, x / 100.),
-                        _ => todo!(),
-                    };
+                    let (size_width, growth) = flex_size(
+                        column_item.target_size.width,
+                        to_width(column_item.original_size, self.texture_size),
+                    );
                     let size_height = match column_item.target_size.height {
                         Val::Px(value) if value == 0. => {
                             if row_index == 1 && column_index == 1 {
                                 Val::Px(value)
                             } else {
-                                Val::Px(to_height(column_item.original_size, self.texture_size) as f32)
+                                Val::Px(
+                                    to_height(column_item.original_size, self.texture_size) as f32
+                                )
                             }
                         }
                         Val::Percent(_) => Val::Auto,
                         other => other,
                     };
-                    let mut child = row_parent.spawn(ImageBundle {
-                        image: UiImage {
-                            texture: self.splitted_texture[n].clone_weak(),
-                            ..default()
-                        },
-                        style: Style {
-                            // size: Size::new(size_width, size_height),
-                            width: size_width,
-                            height: size_height,
-                            margin: UiRect::all(Val::Px(0.)),
-                            flex_grow: growth,
-                            flex_shrink: growth,
-                            ..Default::default()
+                    let source_size = Vec2::new(
+                        to_width(column_item.original_size, self.texture_size) as f32,
+                        to_height(column_item.original_size, self.texture_size) as f32,
+                    );
+                    let style = Style {
+                        // size: Size::new(size_width, size_height),
+                        width: size_width,
+                        height: size_height,
+                        margin: UiRect::all(Val::Px(0.)),
+                        flex_grow: growth,
+                        flex_shrink: growth,
+                        // growing patches start from their original size, whatever the size of
+                        // their image, or without an image
+                        flex_basis: if growth > 0. {
+                            Val::Px(source_size.x)
+                        } else {
+                            Val::Auto
                         },
-                        focus_policy: FocusPolicy::Pass,
                         ..Default::default()
-                    });
+                    };
+                    let mut child = if column_item.stretch != PatchStretch::default() {
+                        row_parent.spawn((
+                            NodeBundle {
+                                style: Style {
+                                    overflow: Overflow::clip(),
+                                    ..style
+                                },
+                                background_color: BackgroundColor(Color::NONE),
+                                focus_policy: FocusPolicy::Pass,
+                                ..Default::default()
+                            },
+                            PatchTiles {
+                                stretch: column_item.stretch,
+                                source_size,
+                                texture: patch_textures[n].clone(),
+                                color: Color::WHITE,
+                                size: None,
+                                tiles: vec![],
+                            },
+                        ))
+                    } else {
+                        match &patch_textures[n] {
+                            TileTexture::Image(texture) => row_parent.spawn(ImageBundle {
+                                image: UiImage {
+                                    texture: texture.clone(),
+                                    ..default()
+                                },
+                                style,
+                                focus_policy: FocusPolicy::Pass,
+                                ..Default::default()
+                            }),
+                            TileTexture::Atlas(atlas, index) => {
+                                row_parent.spawn(AtlasImageBundle {
+                                    texture_atlas: atlas.clone(),
+                                    texture_atlas_image: UiTextureAtlasImage {
+                                        index: *index,
+                                        ..default()
+                                    },
+                                    style,
+                                    focus_policy: FocusPolicy::Pass,
+                                    ..Default::default()
+                                })
+                            }
+                        }
+                    };
+                    child.insert(NinePatchPart::for_patch(
+                        row_index,
+                        column_index,
+                        column_item,
+                    ));
                     if let Some(content_part) = column_item.content.as_ref() {
+                        zones.insert(content_part.clone(), child.id());
                         child.insert(NinePatchContent {
                             content: content_part.clone(),
                             loaded: false,
                             parent,
                         });
-                        if let Some(content_entity) =
+                        if let Some(content_entities) =
                             contents.as_ref().and_then(|m| m.get(content_part))
                         {
-                            child.push_children(&[*content_entity]);
+                            child.push_children(content_entities);
                         }
                     }
+                    patches.push(child.id());
                     n += 1;
                 }
             });
         }
-        commands.entity(parent).push_children(&rows);
+        for row in &rows {
+            commands
+                .entity(*row)
+                .insert(GeneratedNode { nine_patch: parent });
+        }
+        commands
+            .entity(parent)
+            .insert(NinePatchZones { zones })
+            .push_children(&rows);
+        NinePatchParts {
+            rows,
+            patches,
+            nine_patch: nine_patch.clone_weak(),
+            texture: texture.clone_weak(),
+            render_mode: self.textures.render_mode(),
+            texture_size: self.texture_size,
+            patches_layout: self.patches.clone(),
+            contents: contents.clone().unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{app, texture};
+    use bevy::render::{
+        render_resource::{FilterMode, TextureFormat},
+        texture::ImageSampler,
+    };
+
+    fn patch(width: i32, height: i32) -> Patch<()> {
+        Patch {
+            original_size: IVec2::new(width, height),
+            target_size: Size::new(Val::Px(0.), Val::Px(0.)),
+            content: None,
+            stretch: PatchStretch::default(),
+        }
+    }
+
+    /// Opaque white texture
+    fn white_texture(width: u32, height: u32) -> Image {
+        texture(
+            width,
+            height,
+            vec![255; (width * height * 4) as usize],
+            TextureFormat::Rgba8UnormSrgb,
+        )
+    }
+
+    #[test]
+    fn validate_margins() {
+        let nine_patch = NinePatchBuilder::<()>::by_margins(10, 10, 10, 10);
+        assert_eq!(nine_patch.validate(UVec2::new(30, 30)), Ok(()));
+        assert_eq!(
+            nine_patch.validate(UVec2::new(15, 30)),
+            Err(NinePatchError::NegativePatchSize { row: 0, column: 1 })
+        );
+    }
+
+    #[test]
+    fn validate_empty_row() {
+        let nine_patch = NinePatchBuilder::from_patches(vec![vec![patch(10, 10)], vec![]]);
+        assert_eq!(
+            nine_patch.validate(UVec2::new(10, 20)),
+            Err(NinePatchError::EmptyRow { row: 1 })
+        );
+    }
+
+    #[test]
+    fn validate_row_height() {
+        let nine_patch = NinePatchBuilder::from_patches(vec![vec![patch(5, 10), patch(5, 8)]]);
+        assert_eq!(
+            nine_patch.validate(UVec2::new(10, 10)),
+            Err(NinePatchError::RowHeightMismatch { row: 0, column: 1 })
+        );
+    }
+
+    #[test]
+    fn validate_out_of_bounds() {
+        let nine_patch = NinePatchBuilder::from_patches(vec![vec![patch(5, 10), patch(6, 10)]]);
+        assert_eq!(
+            nine_patch.validate(UVec2::new(10, 10)),
+            Err(NinePatchError::PatchOutOfBounds { row: 0, column: 1 })
+        );
+        let nine_patch =
+            NinePatchBuilder::from_patches(vec![vec![patch(10, 6)], vec![patch(10, 5)]]);
+        assert_eq!(
+            nine_patch.validate(UVec2::new(10, 10)),
+            Err(NinePatchError::PatchOutOfBounds { row: 1, column: 0 })
+        );
+    }
+
+    #[test]
+    fn validate_target_size() {
+        for size in [Val::Px(-1.), Val::Percent(f32::INFINITY), Val::Vw(-5.)] {
+            let mut nine_patch = NinePatchBuilder::from_patches(vec![vec![patch(10, 10)]]);
+            nine_patch.patches[0][0].target_size.height = size;
+            assert_eq!(
+                nine_patch.validate(UVec2::new(10, 10)),
+                Err(NinePatchError::UnsupportedTargetSize(size))
+            );
+        }
+    }
+
+    #[test]
+    fn apply_missing_texture() {
+        let mut app = app();
+        let mut textures = app.world.resource_mut::<Assets<Image>>();
+        let nine_patch = NinePatchBuilder::<()>::by_margins(1, 1, 1, 1);
+        assert_eq!(
+            nine_patch
+                .apply(&Handle::default(), &mut textures)
+                .unwrap_err(),
+            NinePatchError::MissingTexture
+        );
+        assert_eq!(
+            nine_patch
+                .apply_single_node(&Handle::default(), &textures)
+                .unwrap_err(),
+            NinePatchError::MissingTexture
+        );
+    }
+
+    #[test]
+    fn apply_compressed_texture() {
+        let mut app = app();
+        let mut textures = app.world.resource_mut::<Assets<Image>>();
+        let mut compressed = white_texture(8, 8);
+        compressed.texture_descriptor.format = TextureFormat::Bc1RgbaUnorm;
+        let handle = textures.add(compressed);
+        let nine_patch = NinePatchBuilder::<()>::by_margins(2, 2, 2, 2);
+        assert_eq!(
+            nine_patch.apply(&handle, &mut textures).unwrap_err(),
+            NinePatchError::UnsupportedTextureFormat(TextureFormat::Bc1RgbaUnorm)
+        );
+    }
+
+    #[test]
+    fn apply_splits_texture() {
+        let mut app = app();
+        let mut textures = app.world.resource_mut::<Assets<Image>>();
+        let handle = textures.add(white_texture(30, 20));
+        let nine_patch = NinePatchBuilder::<()>::by_margins(5, 5, 10, 10)
+            .apply(&handle, &mut textures)
+            .unwrap();
+        let PatchTextures::Split(patch_textures) = &nine_patch.textures else {
+            panic!("textures should be split");
+        };
+        let sizes: Vec<(u32, u32)> = patch_textures
+            .iter()
+            .map(|handle| {
+                let size = textures.get(handle).unwrap().texture_descriptor.size;
+                (size.width, size.height)
+            })
+            .collect();
+        assert_eq!(
+            sizes,
+            vec![
+                (10, 5),
+                (10, 5),
+                (10, 5),
+                (10, 10),
+                (10, 10),
+                (10, 10),
+                (10, 5),
+                (10, 5),
+                (10, 5)
+            ]
+        );
+    }
+
+    /// Patches split from `texture` with margins of 1 pixel, with their format and data
+    fn split_margins(mut texture: Image) -> Vec<(TextureFormat, Vec<u8>)> {
+        texture.sampler_descriptor = ImageSampler::nearest();
+        let mut app = app();
+        let mut textures = app.world.resource_mut::<Assets<Image>>();
+        let handle = textures.add(texture);
+        let nine_patch = NinePatchBuilder::<()>::by_margins(1, 1, 1, 1)
+            .apply(&handle, &mut textures)
+            .unwrap();
+        let PatchTextures::Split(patch_textures) = &nine_patch.textures else {
+            panic!("textures should be split");
+        };
+        patch_textures
+            .iter()
+            .map(|handle| {
+                let patch = textures.get(handle).unwrap();
+                let ImageSampler::Descriptor(sampler) = &patch.sampler_descriptor else {
+                    panic!("sampler should be kept");
+                };
+                assert_eq!(sampler.mag_filter, FilterMode::Nearest);
+                assert_eq!(sampler.min_filter, FilterMode::Nearest);
+                (patch.texture_descriptor.format, patch.data.clone())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn apply_splits_r8_texture() {
+        let patches = split_margins(texture(4, 3, (0..12).collect(), TextureFormat::R8Unorm));
+        let expected: Vec<Vec<u8>> = vec![
+            vec![0],
+            vec![1, 2],
+            vec![3],
+            vec![4],
+            vec![5, 6],
+            vec![7],
+            vec![8],
+            vec![9, 10],
+            vec![11],
+        ];
+        assert_eq!(
+            patches,
+            expected
+                .into_iter()
+                .map(|data| (TextureFormat::R8Unorm, data))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn apply_splits_rgba16_texture() {
+        // 8 bytes per pixel
+        let patches = split_margins(texture(3, 3, (0..72).collect(), TextureFormat::Rgba16Float));
+        assert_eq!(
+            patches,
+            (0..9)
+                .map(|pixel| (
+                    TextureFormat::Rgba16Float,
+                    (pixel * 8..pixel * 8 + 8).collect()
+                ))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn apply_atlas_insets_rects() {
+        let mut app = app();
+        app.add_asset::<TextureAtlas>();
+        let handle = app
+            .world
+            .resource_mut::<Assets<Image>>()
+            .add(white_texture(30, 20));
+        let nine_patch = NinePatchBuilder::<()>::by_margins(5, 5, 10, 10);
+        let np = app
+            .world
+            .resource_scope(|world, mut atlases: Mut<Assets<TextureAtlas>>| {
+                let textures = world.resource::<Assets<Image>>();
+                nine_patch.apply_atlas(&handle, textures, &mut atlases)
+            })
+            .unwrap();
+        let PatchTextures::Atlas(atlas) = &np.textures else {
+            panic!("patches should be in an atlas");
+        };
+        let atlas = app
+            .world
+            .resource::<Assets<TextureAtlas>>()
+            .get(atlas)
+            .unwrap();
+        let rects = nine_patch.patch_rects(np.texture_size);
+        assert_eq!(rects.len(), atlas.textures.len());
+        for (rect, atlas_rect) in rects.iter().zip(&atlas.textures) {
+            assert_eq!(atlas_rect.min, rect.min + 0.5);
+            assert_eq!(atlas_rect.max, rect.max - 0.5);
+        }
+        // empty patches stay empty
+        assert_eq!(
+            atlas_rect(Rect::new(10., 5., 10., 6.)),
+            Rect::new(10., 5.5, 10., 5.5)
+        );
     }
 }
@@ -1,33 +1,73 @@
-use bevy::prelude::*;
 use bevy::reflect::TypePath;
+use bevy::{
+    asset::{HandleId, LoadState},
+    prelude::*,
+    ui::FocusPolicy,
+    utils::HashMap,
+};
 
-use crate::ninepatch::*;
+use crate::{
+    ninepatch::*,
+    single_node::NinePatchSlices,
+    states::NinePatchStates,
+    tiles::{PatchTiles, TileTexture},
+    NinePatchError,
+};
+
+/// Load state of a 9-Patch UI element
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum NinePatchLoadState {
+    /// Waiting for the texture and the `NinePatchBuilder` to be loaded
+    #[default]
+    Pending,
+    /// The nine-patch is displayed
+    Ready,
+    /// The nine-patch could not be created. It is created again when its texture or
+    /// `NinePatchBuilder` is changed, or modified in `Assets`
+    Failed(NinePatchError),
+}
 
 /// State of the current `NinePatch`
 #[derive(Debug, Clone, Component)]
 pub struct NinePatchData<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> {
-    /// Handle of the texture
+    /// Handle of the texture. If not set, the texture of the `NinePatchBuilder` is used. It can be
+    /// changed once the nine-patch is displayed
     pub texture: Handle<Image>,
-    /// Handle to the `NinePatchBuilder`
+    /// Handle to the `NinePatchBuilder`. It can be changed once the nine-patch is displayed
     pub nine_patch: Handle<NinePatchBuilder<T>>,
-    /// Is the element already loaded and displayed
-    pub loaded: bool,
-    /// Entity that should be used for the content
-    pub content: Option<std::collections::HashMap<T, Entity>>,
+    /// Is the element already loaded and displayed, or why it could not be
+    pub load_state: NinePatchLoadState,
+    /// Entities that should be used for the content, by content zone. They are moved to the
+    /// matching content zone when added, and detached when removed
+    pub content: Option<std::collections::HashMap<T, Vec<Entity>>>,
+    /// Placeholder displayed while assets are loading. If not set, the placeholder from
+    /// `NinePatchSettings` is used
+    pub placeholder: Option<NinePatchPlaceholder>,
+    /// Color multiplied with the texture of all patches
+    pub tint: Color,
+    /// Color multiplied with the texture of some patches instead of `tint`, by row and column
+    pub patch_tints: std::collections::HashMap<(usize, usize), Color>,
 }
 
-impl<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> Default for NinePatchData<T> {
+impl<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> Default
+    for NinePatchData<T>
+{
     fn default() -> Self {
         NinePatchData {
             texture: Default::default(),
             nine_patch: Default::default(),
-            loaded: false,
+            load_state: NinePatchLoadState::Pending,
             content: Default::default(),
+            placeholder: None,
+            tint: Color::WHITE,
+            patch_tints: Default::default(),
         }
     }
 }
 
-impl<T: Clone + Send + Sync + Default + Eq + std::hash::Hash + TypePath + 'static> NinePatchData<T> {
+impl<T: Clone + Send + Sync + Default + Eq + std::hash::Hash + TypePath + 'static>
+    NinePatchData<T>
+{
     /// Create a NinePathData with content when there is only one content
     pub fn with_single_content(
         texture: Handle<Image>,
@@ -35,16 +75,57 @@ impl<T: Clone + Send + Sync + Default + Eq + std::hash::Hash + TypePath + 'stati
         content: Entity,
     ) -> NinePatchData<T> {
         let mut content_map = std::collections::HashMap::with_capacity(1);
-        content_map.insert(T::default(), content);
+        content_map.insert(T::default(), vec![content]);
         NinePatchData {
             texture,
             nine_patch,
-            loaded: false,
+            load_state: NinePatchLoadState::Pending,
             content: Some(content_map),
+            placeholder: None,
+            tint: Color::WHITE,
+            patch_tints: Default::default(),
+        }
+    }
+}
+
+impl<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> NinePatchData<T> {
+    /// Color of the patch at `row` and `column`
+    pub fn patch_tint(&self, row: usize, column: usize) -> Color {
+        self.patch_tints
+            .get(&(row, column))
+            .copied()
+            .unwrap_or(self.tint)
+    }
+
+    /// Texture displayed with `nine_patch`: `texture`, or the texture of the `NinePatchBuilder` if
+    /// it is not set
+    fn texture_for(&self, nine_patch: &NinePatchBuilder<T>) -> Handle<Image> {
+        match &nine_patch.texture {
+            Some(texture) if self.texture == Handle::default() => texture.clone(),
+            _ => self.texture.clone(),
         }
     }
 }
 
+/// Event sent when a 9-Patch UI element can't be created or updated, for example because its
+/// `NinePatchBuilder` doesn't fit its texture
+#[derive(Debug, Clone, Copy, Event)]
+pub struct NinePatchErrorEvent {
+    /// Entity holding the `NinePatchData`
+    pub entity: Entity,
+    /// Why the nine-patch couldn't be created
+    pub error: NinePatchError,
+}
+
+/// Event sent when a 9-Patch UI element becomes ready or fails to be created
+#[derive(Debug, Clone, Copy, Event)]
+pub struct NinePatchLoadEvent {
+    /// Entity holding the `NinePatchData`
+    pub entity: Entity,
+    /// New load state of the nine-patch, `Ready` or `Failed`
+    pub load_state: NinePatchLoadState,
+}
+
 #[derive(Bundle)]
 /// Component Bundle to place the NinePatch
 pub struct NinePatchBundle<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> {
@@ -60,7 +141,9 @@ pub struct NinePatchBundle<T: Clone + Send + Sync + Eq + std::hash::Hash + TypeP
     pub global_transform: GlobalTransform,
 }
 
-impl<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> Default for NinePatchBundle<T> {
+impl<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> Default
+    for NinePatchBundle<T>
+{
     fn default() -> Self {
         NinePatchBundle {
             style: Default::default(),
@@ -76,40 +159,1181 @@ impl<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> Default
 #[derive(Debug, Clone, Copy)]
 pub struct NinePatchPlugin<T: Clone + Send + Sync + 'static = ()> {
     marker: std::marker::PhantomData<T>,
+    /// Registers the `NinePatchRonLoader` for `T`
+    #[cfg(feature = "ron")]
+    ron_loader: Option<fn(&mut App)>,
 }
 
 impl<T: Clone + Send + Sync + 'static> Default for NinePatchPlugin<T> {
     fn default() -> Self {
         NinePatchPlugin {
             marker: Default::default(),
+            // the loader can't be registered for content types that can't be deserialized
+            #[cfg(feature = "ron")]
+            ron_loader: (std::any::TypeId::of::<T>() == std::any::TypeId::of::<()>()).then_some(
+                |app: &mut App| {
+                    app.init_asset_loader::<crate::NinePatchRonLoader<()>>();
+                },
+            ),
         }
     }
 }
-impl<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> Plugin for NinePatchPlugin<T> {
+
+#[cfg(feature = "ron")]
+impl<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> NinePatchPlugin<T> {
+    /// Register the `NinePatchRonLoader` loading `NinePatchBuilder<T>` from `.ninepatch.ron`
+    /// files. It is registered by default for `NinePatchPlugin<()>`. Only one loader can handle
+    /// the extension, so files can only be loaded for one content type.
+    pub fn with_ron_loader(mut self) -> Self
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.ron_loader = Some(|app| {
+            app.init_asset_loader::<crate::NinePatchRonLoader<T>>();
+        });
+        self
+    }
+}
+
+/// Settings for all nine patches, can be changed at any time but only affect nine patches created after
+#[derive(Debug, Clone, Default, Resource)]
+pub struct NinePatchSettings {
+    /// How patches are rendered when the `NinePatchBuilder` doesn't set it
+    pub render_mode: NinePatchRenderMode,
+    /// Placeholder displayed while assets are loading when the `NinePatchData` doesn't set it
+    pub placeholder: Option<NinePatchPlaceholder>,
+}
+
+/// Displayed in place of a 9-Patch UI element while its texture and `NinePatchBuilder` are loading,
+/// with the same `Style`. It is kept if the nine-patch fails to be created.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NinePatchPlaceholder {
+    /// A solid color
+    Color(Color),
+    /// A texture, stretched to the size of the element
+    Image(Handle<Image>),
+}
+
+impl<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> Plugin
+    for NinePatchPlugin<T>
+{
     fn build(&self, app: &mut App) {
         app.add_asset::<NinePatchBuilder<T>>()
-            .add_systems(Update, create_ninepatches::<T>);
+            .add_asset::<TextureAtlas>()
+            .init_resource::<NinePatchSettings>()
+            .init_resource::<PatchCache<T>>()
+            .add_event::<NinePatchErrorEvent>()
+            .add_event::<NinePatchLoadEvent>()
+            .add_systems(
+                Update,
+                (
+                    crate::states::update_states::<T>,
+                    remove_nine_patches::<T>,
+                    retarget_nine_patches::<T>,
+                    reload_nine_patches::<T>,
+                    reload_textures::<T>,
+                    update_contents::<T>,
+                    create_ninepatches::<T>,
+                    crate::zones::attach_zone_children::<T>,
+                    crate::states::offset_contents::<T>,
+                )
+                    .chain(),
+            )
+            .add_systems(Last, evict_patch_cache::<T>)
+            .add_systems(
+                Update,
+                (
+                    crate::sprite::remove_sprites::<T>,
+                    crate::sprite::update_sprites::<T>,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                PostUpdate,
+                (
+                    crate::tint::update_tints::<T>.before(bevy::ui::UiSystem::Layout),
+                    crate::single_node::update_slices::<T>.after(bevy::ui::UiSystem::Layout),
+                ),
+            );
+        if !app.is_plugin_added::<crate::single_node::SingleNodePlugin>() {
+            app.add_plugins(crate::single_node::SingleNodePlugin);
+        }
+        if !app.is_plugin_added::<crate::tiles::TilesPlugin>() {
+            app.add_plugins(crate::tiles::TilesPlugin);
+        }
+        #[cfg(feature = "ron")]
+        if let Some(register) = self.ron_loader {
+            register(app);
+        }
     }
 }
 
+/// Assets a 9-Patch UI element failed to be created with, it is created again when they change
+#[derive(Debug, Clone, Copy, Component)]
+struct FailedAssets {
+    /// `NinePatchBuilder` of the nine-patch
+    nine_patch: HandleId,
+    /// Texture of the nine-patch
+    texture: HandleId,
+}
+
+/// Placeholder displayed by a 9-Patch UI element, removed once the nine-patch is ready
+#[derive(Debug, Clone, Copy, Component)]
+struct NinePatchPlaceholderNode(Entity);
+
+/// Display a placeholder filling the node of a 9-Patch UI element
+fn spawn_placeholder(
+    commands: &mut Commands,
+    entity: Entity,
+    style: &Style,
+    placeholder: &NinePatchPlaceholder,
+) {
+    let placeholder_style = Style {
+        position_type: PositionType::Absolute,
+        width: Val::Percent(100.),
+        height: Val::Percent(100.),
+        ..Default::default()
+    };
+    let placeholder_node = match placeholder {
+        NinePatchPlaceholder::Color(color) => commands.spawn(NodeBundle {
+            style: placeholder_style,
+            background_color: BackgroundColor(*color),
+            focus_policy: FocusPolicy::Pass,
+            ..Default::default()
+        }),
+        NinePatchPlaceholder::Image(texture) => commands.spawn(ImageBundle {
+            style: placeholder_style,
+            image: UiImage {
+                texture: texture.clone(),
+                ..Default::default()
+            },
+            focus_policy: FocusPolicy::Pass,
+            ..Default::default()
+        }),
+    }
+    .id();
+    commands.entity(placeholder_node).insert((
+        GeneratedNode { nine_patch: entity },
+        Name::new("Nine-patch placeholder"),
+    ));
+    insert_node(commands, entity, style.clone());
+    commands
+        .entity(entity)
+        .insert(NinePatchPlaceholderNode(placeholder_node))
+        .add_child(placeholder_node);
+}
+
+/// Patch textures created for each `NinePatchBuilder`, texture and render mode, shared by the
+/// nine-patches using them. Entries are kept while a nine-patch, or a skin of its
+/// `NinePatchStates`, uses them
+#[derive(Resource)]
+struct PatchCache<T> {
+    entries: HashMap<(HandleId, HandleId, NinePatchRenderMode), PatchTextures>,
+    marker: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<T> Default for PatchCache<T> {
+    fn default() -> Self {
+        PatchCache {
+            entries: Default::default(),
+            marker: Default::default(),
+        }
+    }
+}
+
+impl<T> PatchCache<T> {
+    /// Forget the patch textures created for a `NinePatchBuilder`
+    fn remove_builder(&mut self, nine_patch: HandleId) {
+        self.entries.retain(|(key, _, _), _| *key != nine_patch);
+    }
+
+    /// Forget the patch textures created from a texture
+    fn remove_texture(&mut self, texture: HandleId) {
+        self.entries.retain(|(_, key, _), _| *key != texture);
+    }
+}
+
+/// Forget the patch textures that are not used by a nine-patch or a skin of its `NinePatchStates`
+/// anymore, so that they are freed once their patch entities are despawned
 #[allow(clippy::type_complexity)]
+fn evict_patch_cache<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static>(
+    mut cache: ResMut<PatchCache<T>>,
+    nine_patches: Res<Assets<NinePatchBuilder<T>>>,
+    mut removed: RemovedComponents<NinePatchParts<T>>,
+    changed: Query<(), Or<(Changed<NinePatchParts<T>>, Changed<NinePatchStates<T>>)>>,
+    parts: Query<&NinePatchParts<T>>,
+    states: Query<&NinePatchStates<T>>,
+) {
+    let has_removed = removed.iter().count() > 0;
+    if cache.entries.is_empty() || (!has_removed && changed.is_empty()) {
+        return;
+    }
+    let skins = states.iter().flat_map(NinePatchStates::skins).map(|skin| {
+        let texture = match nine_patches
+            .get(&skin.nine_patch)
+            .and_then(|nine_patch| nine_patch.texture.as_ref())
+        {
+            Some(texture) if skin.texture == Handle::default() => texture.id(),
+            _ => skin.texture.id(),
+        };
+        (skin.nine_patch.id(), texture)
+    });
+    let used: std::collections::HashSet<(HandleId, HandleId)> = parts
+        .iter()
+        .map(|parts| (parts.nine_patch.id(), parts.texture.id()))
+        .chain(skins)
+        .collect();
+    cache
+        .entries
+        .retain(|(nine_patch, texture, _), _| used.contains(&(*nine_patch, *texture)));
+}
+
+/// Apply a `NinePatchBuilder` to a texture with its render mode, reusing the patch textures
+/// previously created for this texture
+fn apply_nine_patch<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static>(
+    nine_patch: &NinePatchBuilder<T>,
+    nine_patch_handle: &Handle<NinePatchBuilder<T>>,
+    texture: &Handle<Image>,
+    settings: &NinePatchSettings,
+    cache: &mut PatchCache<T>,
+    textures: &mut Assets<Image>,
+    atlases: &mut Assets<TextureAtlas>,
+) -> Result<NinePatch<T>, NinePatchError> {
+    let render_mode = nine_patch.render_mode.unwrap_or(settings.render_mode);
+    let key = (nine_patch_handle.id(), texture.id(), render_mode);
+    if let Some(patch_textures) = cache.entries.get(&key) {
+        let texture_size = textures
+            .get(texture)
+            .ok_or(NinePatchError::MissingTexture)?
+            .texture_descriptor
+            .size;
+        return Ok(nine_patch.with_patch_textures(texture, texture_size, patch_textures.clone()));
+    }
+    let np = match render_mode {
+        NinePatchRenderMode::SplitTextures => nine_patch.apply(texture, textures),
+        NinePatchRenderMode::TextureAtlas => nine_patch.apply_atlas(texture, textures, atlases),
+        NinePatchRenderMode::SingleNode => nine_patch.apply_single_node(texture, textures),
+    }?;
+    cache.entries.insert(key, np.textures.clone());
+    Ok(np)
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
 fn create_ninepatches<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static>(
     mut commands: Commands,
-    mut nine_patches: ResMut<Assets<NinePatchBuilder<T>>>,
+    asset_server: Res<AssetServer>,
+    nine_patches: Res<Assets<NinePatchBuilder<T>>>,
+    mut cache: ResMut<PatchCache<T>>,
     mut textures: ResMut<Assets<Image>>,
-    mut patches_query: Query<(Entity, &mut NinePatchData<T>, &Style)>,
+    mut atlases: ResMut<Assets<TextureAtlas>>,
+    settings: Res<NinePatchSettings>,
+    mut patches_query: Query<(
+        Entity,
+        &mut NinePatchData<T>,
+        &Style,
+        Option<&NinePatchPlaceholderNode>,
+    )>,
+    mut error_events: EventWriter<NinePatchErrorEvent>,
+    mut load_events: EventWriter<NinePatchLoadEvent>,
 ) {
-    for (entity, mut data, style) in patches_query.iter_mut() {
-        if !data.loaded {
-            if let Some(nine_patch) = nine_patches.get_mut(&data.nine_patch) {
-                if textures.get(&data.texture).is_none() {
+    for (entity, mut data, style, placeholder_node) in patches_query.iter_mut() {
+        if data.load_state != NinePatchLoadState::Pending {
+            continue;
+        }
+        let mut texture = data.texture.clone();
+        let result = match nine_patches.get(&data.nine_patch) {
+            None if asset_server.get_load_state(&data.nine_patch) == LoadState::Failed => {
+                Some(Err(NinePatchError::BuilderLoadFailed))
+            }
+            // builder is not available yet, will try next loop
+            None => None,
+            Some(nine_patch) => {
+                texture = data.texture_for(nine_patch);
+                if texture == Handle::default() {
+                    Some(Err(NinePatchError::MissingTexture))
+                } else if textures.get(&texture).is_some() {
+                    let np = apply_nine_patch(
+                        nine_patch,
+                        &data.nine_patch,
+                        &texture,
+                        &settings,
+                        &mut cache,
+                        &mut textures,
+                        &mut atlases,
+                    );
+                    Some(np.map(|np| {
+                        np.add_with_parent(
+                            &mut commands,
+                            entity,
+                            style,
+                            &data.content,
+                            &data.nine_patch,
+                            &texture,
+                        )
+                    }))
+                } else if asset_server.get_load_state(&texture) == LoadState::Failed {
+                    Some(Err(NinePatchError::TextureLoadFailed))
+                } else {
                     // texture is not available yet, will try next loop
+                    None
+                }
+            }
+        };
+        let Some(result) = result else {
+            if placeholder_node.is_none() {
+                let placeholder = data.placeholder.as_ref().or(settings.placeholder.as_ref());
+                if let Some(placeholder) = placeholder {
+                    spawn_placeholder(&mut commands, entity, style, placeholder);
+                }
+            }
+            continue;
+        };
+        data.load_state = match result {
+            Ok(parts) => {
+                commands
+                    .entity(entity)
+                    .insert(parts)
+                    .remove::<FailedAssets>();
+                if let Some(placeholder_node) = placeholder_node {
+                    commands.entity(placeholder_node.0).despawn_recursive();
+                    commands.entity(entity).remove::<NinePatchPlaceholderNode>();
+                }
+                NinePatchLoadState::Ready
+            }
+            Err(error) => {
+                error!("could not create nine-patch for {entity:?}: {error}");
+                error_events.send(NinePatchErrorEvent { entity, error });
+                // the assets won't change by themselves, only retry when they do
+                commands.entity(entity).insert(FailedAssets {
+                    nine_patch: data.nine_patch.id(),
+                    texture: texture.id(),
+                });
+                NinePatchLoadState::Failed(error)
+            }
+        };
+        load_events.send(NinePatchLoadEvent {
+            entity,
+            load_state: data.load_state,
+        });
+    }
+}
+
+/// Remove the entities generated for a nine-patch. Entities from `NinePatchData::content` are kept
+/// and detached from their content zones, other entities placed in content zones are despawned.
+fn despawn_parts<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static>(
+    commands: &mut Commands,
+    entity: Entity,
+    data: &NinePatchData<T>,
+    parts: &NinePatchParts<T>,
+) {
+    for content in data
+        .content
+        .iter()
+        .flat_map(|content| content.values().flatten())
+    {
+        // content entities may have been despawned since they were last pruned
+        if let Some(mut content) = commands.get_entity(*content) {
+            content.remove_parent();
+        }
+    }
+    for row in &parts.rows {
+        commands.entity(*row).despawn_recursive();
+    }
+    commands
+        .entity(entity)
+        .remove::<(NinePatchParts<T>, NinePatchZones<T>, NinePatchSlices)>();
+}
+
+/// Forget the content entities that have been despawned, and move the content entities of
+/// displayed nine-patches to their content zone when `NinePatchData::content` changes
+#[allow(clippy::type_complexity)]
+fn update_contents<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static>(
+    mut commands: Commands,
+    entities: &bevy::ecs::entity::Entities,
+    mut nine_patches: Query<(
+        &mut NinePatchData<T>,
+        Option<(&mut NinePatchParts<T>, &NinePatchZones<T>)>,
+    )>,
+) {
+    for (mut data, parts) in nine_patches.iter_mut() {
+        let despawned = data
+            .content
+            .iter()
+            .flat_map(|content| content.values().flatten())
+            .any(|content| !entities.contains(*content));
+        if despawned {
+            for content in data
+                .content
+                .iter_mut()
+                .flat_map(|content| content.values_mut())
+            {
+                content.retain(|content| entities.contains(*content));
+            }
+        }
+        let Some((mut parts, zones)) = parts else {
+            continue;
+        };
+        if !data.is_changed() {
+            continue;
+        }
+        let contents = data.content.clone().unwrap_or_default();
+        if contents == parts.contents {
+            continue;
+        }
+        let in_zone = |contents: &std::collections::HashMap<T, Vec<Entity>>, key, entity| {
+            contents
+                .get(key)
+                .is_some_and(|entities: &Vec<Entity>| entities.contains(entity))
+        };
+        // entities moving from one zone to another are detached first, then added to their new zone
+        for (key, previous) in &parts.contents {
+            for entity in previous {
+                if !in_zone(&contents, key, entity) && entities.contains(*entity) {
+                    commands.entity(*entity).remove_parent();
+                }
+            }
+        }
+        for (key, current) in &contents {
+            let Some(zone) = zones.get(key) else {
+                continue;
+            };
+            for entity in current {
+                if !in_zone(&parts.contents, key, entity) {
+                    commands.entity(zone).add_child(*entity);
+                }
+            }
+        }
+        parts.contents = contents;
+    }
+}
+
+/// Remove the entities generated for nine-patches whose `NinePatchData` has been removed, or that
+/// have been despawned without their children. Entities placed in content zones are kept and
+/// detached.
+fn remove_nine_patches<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static>(
+    mut commands: Commands,
+    mut removed: RemovedComponents<NinePatchData<T>>,
+    generated: Query<(Entity, &GeneratedNode)>,
+    zones: Query<(&NinePatchContent<T>, &Children, Option<&PatchTiles>)>,
+) {
+    let removed: std::collections::HashSet<Entity> = removed.iter().collect();
+    if removed.is_empty() {
+        return;
+    }
+    for (zone, children, tiles) in zones.iter() {
+        if !removed.contains(&zone.parent) {
+            continue;
+        }
+        for child in children.iter() {
+            if tiles.is_some_and(|tiles| tiles.tiles.contains(child)) {
+                continue;
+            }
+            if let Some(mut child) = commands.get_entity(*child) {
+                child.remove_parent();
+            }
+        }
+    }
+    for (entity, node) in generated.iter() {
+        if removed.contains(&node.nine_patch) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    for entity in removed {
+        if let Some(mut entity) = commands.get_entity(entity) {
+            entity.remove::<(
+                NinePatchParts<T>,
+                NinePatchZones<T>,
+                NinePatchSlices,
+                NinePatchPlaceholderNode,
+            )>();
+        }
+    }
+}
+
+/// Patch entities of the nine-patches, with the component holding their texture
+type PatchEntities<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Option<&'static mut UiImage>,
+        Option<&'static mut Handle<TextureAtlas>>,
+        Option<&'static mut PatchTiles>,
+    ),
+>;
+
+/// Draw the entities of a nine-patch with the textures of `np`, created for the same patches from
+/// a texture of the same size
+fn swap_patch_textures<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static>(
+    np: &NinePatch<T>,
+    parts: &mut NinePatchParts<T>,
+    slices: Option<Mut<NinePatchSlices>>,
+    texture: &Handle<Image>,
+    patch_entities: &mut PatchEntities,
+) {
+    for (index, patch) in parts.patches.iter().enumerate() {
+        let Ok((image, atlas, tiles)) = patch_entities.get_mut(*patch) else {
+            continue;
+        };
+        let tile_texture = match &np.textures {
+            PatchTextures::Split(patch_textures) => {
+                if let Some(mut image) = image {
+                    image.texture = patch_textures[index].clone();
+                }
+                TileTexture::Image(patch_textures[index].clone())
+            }
+            PatchTextures::Atlas(patch_atlas) => {
+                if let Some(mut atlas) = atlas {
+                    *atlas = patch_atlas.clone();
+                }
+                TileTexture::Atlas(patch_atlas.clone(), index)
+            }
+            PatchTextures::Single => continue,
+        };
+        if let Some(mut tiles) = tiles {
+            tiles.texture = tile_texture;
+            // respawn the repetitions with the new texture
+            tiles.size = None;
+        }
+    }
+    if let Some(mut slices) = slices {
+        slices.set_texture(texture);
+    }
+    parts.texture = texture.clone_weak();
+}
+
+/// Update the nine-patches whose `NinePatchData` now uses another texture or `NinePatchBuilder`.
+/// Patch entities are kept when the patches, the render mode and the texture size don't change,
+/// otherwise the nine-patch is rebuilt. Nine-patches that failed are created again.
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn retarget_nine_patches<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static>(
+    mut commands: Commands,
+    nine_patches: Res<Assets<NinePatchBuilder<T>>>,
+    mut cache: ResMut<PatchCache<T>>,
+    mut textures: ResMut<Assets<Image>>,
+    mut atlases: ResMut<Assets<TextureAtlas>>,
+    settings: Res<NinePatchSettings>,
+    mut patches_query: Query<
+        (
+            Entity,
+            &mut NinePatchData<T>,
+            Option<&mut NinePatchParts<T>>,
+            Option<&mut NinePatchSlices>,
+            Option<&FailedAssets>,
+        ),
+        Changed<NinePatchData<T>>,
+    >,
+    mut patch_entities: PatchEntities,
+) {
+    for (entity, mut data, parts, slices, failed) in patches_query.iter_mut() {
+        let nine_patch = nine_patches.get(&data.nine_patch);
+        let texture = match nine_patch {
+            Some(nine_patch) => data.texture_for(nine_patch),
+            None => data.texture.clone(),
+        };
+        if let NinePatchLoadState::Failed(_) = data.load_state {
+            if failed.is_some_and(|failed| {
+                failed.nine_patch != data.nine_patch.id() || failed.texture != texture.id()
+            }) {
+                data.load_state = NinePatchLoadState::Pending;
+            }
+            continue;
+        }
+        let Some(mut parts) = parts else {
+            continue;
+        };
+        if data.load_state != NinePatchLoadState::Ready {
+            continue;
+        }
+        if data.nine_patch == parts.nine_patch && texture == parts.texture {
+            continue;
+        }
+        let same_layout = nine_patch.filter(|nine_patch| {
+            nine_patch.patches == parts.patches_layout
+                && nine_patch.render_mode.unwrap_or(settings.render_mode) == parts.render_mode
+                && textures
+                    .get(&texture)
+                    .is_some_and(|image| image.texture_descriptor.size == parts.texture_size)
+        });
+        if let Some(nine_patch) = same_layout {
+            let np = apply_nine_patch(
+                nine_patch,
+                &data.nine_patch,
+                &texture,
+                &settings,
+                &mut cache,
+                &mut textures,
+                &mut atlases,
+            );
+            // errors are reported when creating the nine-patch again
+            if let Ok(np) = np {
+                swap_patch_textures(&np, &mut parts, slices, &texture, &mut patch_entities);
+                parts.nine_patch = data.nine_patch.clone_weak();
+                continue;
+            }
+        }
+        despawn_parts(&mut commands, entity, &data, &parts);
+        data.load_state = NinePatchLoadState::Pending;
+    }
+}
+
+/// Rebuild the nine-patches whose `NinePatchBuilder` has been modified, and create again the
+/// nine-patches that failed with it
+#[allow(clippy::type_complexity)]
+fn reload_nine_patches<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static>(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<NinePatchBuilder<T>>>,
+    nine_patches: Res<Assets<NinePatchBuilder<T>>>,
+    mut cache: ResMut<PatchCache<T>>,
+    settings: Res<NinePatchSettings>,
+    mut patches_query: Query<(
+        Entity,
+        &mut NinePatchData<T>,
+        Option<&NinePatchParts<T>>,
+        Option<&FailedAssets>,
+    )>,
+) {
+    for event in events.iter() {
+        let (handle, is_modified) = match event {
+            AssetEvent::Created { handle } => (handle, false),
+            AssetEvent::Modified { handle } => (handle, true),
+            AssetEvent::Removed { handle } => {
+                cache.remove_builder(handle.id());
+                continue;
+            }
+        };
+        let Some(nine_patch) = nine_patches.get(handle) else {
+            continue;
+        };
+        let mut invalidated = false;
+        for (entity, mut data, parts, failed) in patches_query.iter_mut() {
+            if failed.is_some_and(|failed| failed.nine_patch == handle.id()) {
+                data.load_state = NinePatchLoadState::Pending;
+                continue;
+            }
+            let Some(parts) = parts else {
+                continue;
+            };
+            if !is_modified
+                || &data.nine_patch != handle
+                || data.load_state != NinePatchLoadState::Ready
+            {
+                continue;
+            }
+            // the patches, the render mode or the texture of the builder may have changed
+            if parts.patches_layout == nine_patch.patches
+                && parts.render_mode == nine_patch.render_mode.unwrap_or(settings.render_mode)
+                && parts.texture == data.texture_for(nine_patch)
+            {
+                continue;
+            }
+            invalidated = true;
+            despawn_parts(&mut commands, entity, &data, parts);
+            data.load_state = NinePatchLoadState::Pending;
+        }
+        if invalidated {
+            cache.remove_builder(handle.id());
+        }
+    }
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn reload_textures<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static>(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<Image>>,
+    nine_patches: Res<Assets<NinePatchBuilder<T>>>,
+    mut cache: ResMut<PatchCache<T>>,
+    mut textures: ResMut<Assets<Image>>,
+    mut atlases: ResMut<Assets<TextureAtlas>>,
+    settings: Res<NinePatchSettings>,
+    mut patches_query: Query<(
+        Entity,
+        &mut NinePatchData<T>,
+        Option<&mut NinePatchParts<T>>,
+        Option<&mut NinePatchSlices>,
+        Option<&FailedAssets>,
+    )>,
+    mut patch_entities: PatchEntities,
+    mut error_events: EventWriter<NinePatchErrorEvent>,
+) {
+    for event in events.iter() {
+        let (handle, is_modified) = match event {
+            AssetEvent::Created { handle } => (handle, false),
+            AssetEvent::Modified { handle } => (handle, true),
+            AssetEvent::Removed { handle } => {
+                cache.remove_texture(handle.id());
+                continue;
+            }
+        };
+        if is_modified {
+            // patches split from the previous version of the texture can't be reused
+            cache.remove_texture(handle.id());
+        }
+        for (entity, mut data, parts, slices, failed) in patches_query.iter_mut() {
+            if failed.is_some_and(|failed| failed.texture == handle.id()) {
+                data.load_state = NinePatchLoadState::Pending;
+                continue;
+            }
+            let Some(mut parts) = parts else {
+                continue;
+            };
+            if !is_modified
+                || &parts.texture != handle
+                || data.load_state != NinePatchLoadState::Ready
+            {
+                continue;
+            }
+            let Some(nine_patch) = nine_patches.get(&data.nine_patch) else {
+                continue;
+            };
+            let Some(texture) = textures.get(handle) else {
+                continue;
+            };
+            if texture.texture_descriptor.size != parts.texture_size {
+                // patches sizes depend on the texture size, the nine-patch must be rebuilt
+                despawn_parts(&mut commands, entity, &data, &parts);
+                data.load_state = NinePatchLoadState::Pending;
+                continue;
+            }
+            let np = match apply_nine_patch(
+                nine_patch,
+                &data.nine_patch,
+                handle,
+                &settings,
+                &mut cache,
+                &mut textures,
+                &mut atlases,
+            ) {
+                Ok(np) => np,
+                Err(error) => {
+                    error!("could not update nine-patch for {entity:?}: {error}");
+                    error_events.send(NinePatchErrorEvent { entity, error });
                     continue;
                 }
-                let np = nine_patch.apply(&data.texture, &mut textures);
-                np.add_with_parent(&mut commands, entity, style, &data.content);
-                data.loaded = true;
+            };
+            swap_patch_textures(&np, &mut parts, slices, handle, &mut patch_entities);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::texture;
+    use bevy::render::render_resource::TextureFormat;
+
+    fn app() -> App {
+        app_with_content::<()>()
+    }
+
+    fn app_with_content<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static>() -> App
+    {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .add_asset::<Image>()
+            .add_plugins(NinePatchPlugin::<T>::default());
+        app
+    }
+
+    /// Texture whose bytes are all `value`
+    fn image(width: u32, height: u32, value: u8) -> Image {
+        texture(
+            width,
+            height,
+            vec![value; (width * height * 4) as usize],
+            TextureFormat::Rgba8UnormSrgb,
+        )
+    }
+
+    /// Spawn a nine-patch with `content` in its content zone, and wait until it is ready
+    fn spawn_ready(
+        app: &mut App,
+        texture: &Handle<Image>,
+        nine_patch: &Handle<NinePatchBuilder>,
+        content: Entity,
+    ) -> Entity {
+        let entity = app
+            .world
+            .spawn(NinePatchBundle {
+                nine_patch_data: NinePatchData::with_single_content(
+                    texture.clone(),
+                    nine_patch.clone(),
+                    content,
+                ),
+                ..Default::default()
+            })
+            .id();
+        assert_eq!(load_state(app, entity), NinePatchLoadState::Ready);
+        entity
+    }
+
+    fn parts(app: &App, entity: Entity) -> &NinePatchParts<()> {
+        app.world.get::<NinePatchParts<()>>(entity).unwrap()
+    }
+
+    /// First byte of the texture of each patch
+    fn patch_values(app: &App, entity: Entity) -> Vec<u8> {
+        let textures = app.world.resource::<Assets<Image>>();
+        parts(app, entity)
+            .patches()
+            .iter()
+            .map(|patch| {
+                let image = app.world.get::<UiImage>(*patch).unwrap();
+                textures.get(&image.texture).unwrap().data[0]
+            })
+            .collect()
+    }
+
+    /// Content zone of a nine-patch with a single content
+    fn content_zone(app: &App, entity: Entity) -> Entity {
+        app.world
+            .get::<NinePatchZones<()>>(entity)
+            .unwrap()
+            .get(&())
+            .unwrap()
+    }
+
+    fn parent(app: &App, entity: Entity) -> Option<Entity> {
+        app.world.get::<Parent>(entity).map(Parent::get)
+    }
+
+    /// Update until the nine-patch is no longer pending, and return its load state
+    fn load_state(app: &mut App, entity: Entity) -> NinePatchLoadState {
+        for _ in 0..100 {
+            app.update();
+            let load_state = app
+                .world
+                .get::<NinePatchData<()>>(entity)
+                .unwrap()
+                .load_state;
+            if load_state != NinePatchLoadState::Pending {
+                return load_state;
             }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        NinePatchLoadState::Pending
+    }
+
+    #[test]
+    fn texture_load_failed() {
+        let mut app = app();
+        let texture = app.world.resource::<AssetServer>().load("missing.png");
+        let nine_patch = app
+            .world
+            .resource_mut::<Assets<NinePatchBuilder>>()
+            .add(NinePatchBuilder::by_margins(1, 1, 1, 1));
+        let entity = app
+            .world
+            .spawn(NinePatchBundle::<()> {
+                nine_patch_data: NinePatchData {
+                    texture,
+                    nine_patch,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .id();
+        assert_eq!(
+            load_state(&mut app, entity),
+            NinePatchLoadState::Failed(NinePatchError::TextureLoadFailed)
+        );
+    }
+
+    #[test]
+    fn builder_load_failed() {
+        let mut app = app();
+        let nine_patch = app
+            .world
+            .resource::<AssetServer>()
+            .load("missing.ninepatch.ron");
+        let entity = app
+            .world
+            .spawn(NinePatchBundle::<()> {
+                nine_patch_data: NinePatchData {
+                    nine_patch,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .id();
+        assert_eq!(
+            load_state(&mut app, entity),
+            NinePatchLoadState::Failed(NinePatchError::BuilderLoadFailed)
+        );
+    }
+
+    #[test]
+    fn missing_texture() {
+        let mut app = app();
+        let nine_patch = app
+            .world
+            .resource_mut::<Assets<NinePatchBuilder>>()
+            .add(NinePatchBuilder::by_margins(1, 1, 1, 1));
+        let entity = app
+            .world
+            .spawn(NinePatchBundle::<()> {
+                nine_patch_data: NinePatchData {
+                    nine_patch,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .id();
+        assert_eq!(
+            load_state(&mut app, entity),
+            NinePatchLoadState::Failed(NinePatchError::MissingTexture)
+        );
+    }
+
+    #[test]
+    fn reload_modified_texture() {
+        let mut app = app();
+        let texture = app
+            .world
+            .resource_mut::<Assets<Image>>()
+            .add(image(30, 30, 1));
+        let nine_patch = app
+            .world
+            .resource_mut::<Assets<NinePatchBuilder>>()
+            .add(NinePatchBuilder::by_margins(10, 10, 10, 10));
+        let content = app.world.spawn_empty().id();
+        let entity = spawn_ready(&mut app, &texture, &nine_patch, content);
+        let patches = parts(&app, entity).patches().to_vec();
+        let zone = content_zone(&app, entity);
+        assert_eq!(patch_values(&app, entity), vec![1; 9]);
+        // patch textures are swapped, the hierarchy is kept
+        *app.world
+            .resource_mut::<Assets<Image>>()
+            .get_mut(&texture)
+            .unwrap() = image(30, 30, 7);
+        app.update();
+        app.update();
+        assert_eq!(parts(&app, entity).patches(), &patches[..]);
+        assert_eq!(patch_values(&app, entity), vec![7; 9]);
+        assert_eq!(parent(&app, content), Some(zone));
+        // patches sizes depend on the texture size
+        *app.world
+            .resource_mut::<Assets<Image>>()
+            .get_mut(&texture)
+            .unwrap() = image(60, 60, 8);
+        app.update();
+        app.update();
+        assert_eq!(load_state(&mut app, entity), NinePatchLoadState::Ready);
+        assert!(patches
+            .iter()
+            .all(|patch| app.world.get_entity(*patch).is_none()));
+        assert_eq!(patch_values(&app, entity), vec![8; 9]);
+        assert_eq!(parent(&app, content), Some(content_zone(&app, entity)));
+    }
+
+    #[test]
+    fn reload_modified_builder() {
+        let mut app = app();
+        let texture = app
+            .world
+            .resource_mut::<Assets<Image>>()
+            .add(image(30, 30, 1));
+        let other_texture = app
+            .world
+            .resource_mut::<Assets<Image>>()
+            .add(image(30, 30, 2));
+        let mut builder = NinePatchBuilder::by_margins(10, 10, 10, 10);
+        builder.texture = Some(texture);
+        let nine_patch = app
+            .world
+            .resource_mut::<Assets<NinePatchBuilder>>()
+            .add(builder);
+        let content = app.world.spawn_empty().id();
+        let entity = spawn_ready(&mut app, &Handle::default(), &nine_patch, content);
+        let modify = |app: &mut App, modify: &dyn Fn(&mut NinePatchBuilder)| {
+            let patches = parts(app, entity).patches().to_vec();
+            modify(
+                app.world
+                    .resource_mut::<Assets<NinePatchBuilder>>()
+                    .get_mut(&nine_patch)
+                    .unwrap(),
+            );
+            // asset events are sent at the end of the frame
+            app.update();
+            app.update();
+            assert_eq!(load_state(app, entity), NinePatchLoadState::Ready);
+            assert_eq!(parent(app, content), Some(content_zone(app, entity)));
+            // whether the patches have been rebuilt
+            patches
+                .iter()
+                .all(|patch| app.world.get_entity(*patch).is_none())
+        };
+        assert!(!modify(&mut app, &|_| ()));
+        assert!(modify(&mut app, &|nine_patch| {
+            nine_patch.patches = NinePatchBuilder::by_margins(5, 5, 5, 5).patches;
+        }));
+        assert!(modify(&mut app, &|nine_patch| {
+            nine_patch.texture = Some(other_texture.clone());
+        }));
+        assert_eq!(patch_values(&app, entity), vec![2; 9]);
+        assert!(modify(&mut app, &|nine_patch| {
+            nine_patch.render_mode = Some(NinePatchRenderMode::TextureAtlas);
+        }));
+        assert_eq!(
+            parts(&app, entity).render_mode(),
+            NinePatchRenderMode::TextureAtlas
+        );
+    }
+
+    #[test]
+    fn remove_nine_patch() {
+        let mut app = app();
+        let texture = app
+            .world
+            .resource_mut::<Assets<Image>>()
+            .add(image(30, 30, 1));
+        let nine_patch = app
+            .world
+            .resource_mut::<Assets<NinePatchBuilder>>()
+            .add(NinePatchBuilder::by_margins(10, 10, 10, 10));
+        let content = app.world.spawn_empty().id();
+        let entity = spawn_ready(&mut app, &texture, &nine_patch, content);
+        let mut generated = app.world.query_filtered::<Entity, With<GeneratedNode>>();
+        assert_eq!(generated.iter(&app.world).count(), 3);
+        app.world.entity_mut(entity).remove::<NinePatchData<()>>();
+        app.update();
+        assert_eq!(generated.iter(&app.world).count(), 0);
+        assert!(app.world.get_entity(content).is_some());
+        assert_eq!(parent(&app, content), None);
+        assert!(app.world.get::<NinePatchParts<()>>(entity).is_none());
+        assert!(app.world.get::<NinePatchZones<()>>(entity).is_none());
+    }
+
+    #[test]
+    fn evict_cached_patches() {
+        let mut app = app();
+        let texture = app
+            .world
+            .resource_mut::<Assets<Image>>()
+            .add(image(30, 30, 1));
+        let other_texture = app
+            .world
+            .resource_mut::<Assets<Image>>()
+            .add(image(30, 30, 2));
+        let nine_patch = app
+            .world
+            .resource_mut::<Assets<NinePatchBuilder>>()
+            .add(NinePatchBuilder::by_margins(10, 10, 10, 10));
+        let cached = |app: &App| app.world.resource::<PatchCache<()>>().entries.len();
+        let spawn = |app: &mut App, texture: &Handle<Image>| {
+            let content = app.world.spawn_empty().id();
+            spawn_ready(app, texture, &nine_patch, content)
+        };
+        let first = spawn(&mut app, &texture);
+        let second = spawn(&mut app, &texture);
+        let third = spawn(&mut app, &other_texture);
+        // nine-patches with the same texture share their patch textures
+        assert_eq!(cached(&app), 2);
+        assert_eq!(app.world.resource::<Assets<Image>>().len(), 2 + 2 * 9);
+        app.world.entity_mut(third).despawn_recursive();
+        app.update();
+        assert_eq!(cached(&app), 1);
+        app.world.entity_mut(first).remove::<NinePatchData<()>>();
+        app.update();
+        assert_eq!(cached(&app), 1);
+        app.world.entity_mut(second).remove::<NinePatchData<()>>();
+        app.update();
+        assert_eq!(cached(&app), 0);
+        // patch textures are freed once nothing uses them, after their dropped handles are
+        // processed
+        for _ in 0..3 {
+            app.update();
         }
+        assert_eq!(app.world.resource::<Assets<Image>>().len(), 2);
+    }
+
+    #[test]
+    fn retarget_nine_patch() {
+        let mut app = app();
+        let mut textures = app.world.resource_mut::<Assets<Image>>();
+        let texture = textures.add(image(30, 30, 1));
+        let same_size = textures.add(image(30, 30, 2));
+        let other_size = textures.add(image(60, 60, 3));
+        let mut nine_patches = app.world.resource_mut::<Assets<NinePatchBuilder>>();
+        let nine_patch = nine_patches.add(NinePatchBuilder::by_margins(10, 10, 10, 10));
+        let same_layout = nine_patches.add(NinePatchBuilder::by_margins(10, 10, 10, 10));
+        let other_layout = nine_patches.add(NinePatchBuilder::by_margins(5, 5, 5, 5));
+        let content = app.world.spawn_empty().id();
+        let entity = spawn_ready(&mut app, &texture, &nine_patch, content);
+        let retarget = |app: &mut App, retarget: &dyn Fn(&mut NinePatchData<()>)| {
+            let patches = parts(app, entity).patches().to_vec();
+            retarget(&mut app.world.get_mut::<NinePatchData<()>>(entity).unwrap());
+            app.update();
+            assert_eq!(load_state(app, entity), NinePatchLoadState::Ready);
+            assert_eq!(parent(app, content), Some(content_zone(app, entity)));
+            // whether the patches have been rebuilt
+            patches
+                .iter()
+                .all(|patch| app.world.get_entity(*patch).is_none())
+        };
+        assert!(!retarget(&mut app, &|data| data.texture = same_size.clone()));
+        assert_eq!(patch_values(&app, entity), vec![2; 9]);
+        assert!(!retarget(&mut app, &|data| data.nine_patch = same_layout.clone()));
+        assert!(retarget(&mut app, &|data| data.texture = other_size.clone()));
+        assert_eq!(patch_values(&app, entity), vec![3; 9]);
+        assert!(retarget(&mut app, &|data| data.nine_patch = other_layout.clone()));
+    }
+
+    #[test]
+    fn update_content_entities() {
+        let mut app = app_with_content::<u8>();
+        let texture = app
+            .world
+            .resource_mut::<Assets<Image>>()
+            .add(image(20, 10, 1));
+        let patch = |content| Patch {
+            original_size: IVec2::new(10, 10),
+            target_size: Size::new(Val::Auto, Val::Auto),
+            content: Some(content),
+            stretch: PatchStretch::default(),
+        };
+        let nine_patch = app
+            .world
+            .resource_mut::<Assets<NinePatchBuilder<u8>>>()
+            .add(NinePatchBuilder::from_patches(vec![vec![
+                patch(0),
+                patch(1),
+            ]]));
+        let first = app.world.spawn_empty().id();
+        let second = app.world.spawn_empty().id();
+        let entity = app
+            .world
+            .spawn(NinePatchBundle {
+                nine_patch_data: NinePatchData {
+                    texture,
+                    nine_patch,
+                    content: Some([(0, vec![first])].into()),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .id();
+        app.update();
+        let zones = app.world.get::<NinePatchZones<u8>>(entity).unwrap();
+        let zones = [zones.get(&0).unwrap(), zones.get(&1).unwrap()];
+        let set_content = |app: &mut App, content: &[(u8, Vec<Entity>)]| {
+            let mut data = app.world.get_mut::<NinePatchData<u8>>(entity).unwrap();
+            data.content = Some(content.iter().cloned().collect());
+            app.update();
+        };
+        assert_eq!(parent(&app, first), Some(zones[0]));
+        set_content(&mut app, &[(0, vec![first]), (1, vec![second])]);
+        assert_eq!(parent(&app, second), Some(zones[1]));
+        // moved to another zone
+        set_content(&mut app, &[(1, vec![second, first])]);
+        assert_eq!(parent(&app, first), Some(zones[1]));
+        // removed entities are detached
+        set_content(&mut app, &[(1, vec![first])]);
+        assert_eq!(parent(&app, second), None);
+        assert_eq!(parent(&app, first), Some(zones[1]));
+        // despawned entities are forgotten
+        app.world.entity_mut(first).despawn_recursive();
+        app.update();
+        let data = app.world.get::<NinePatchData<u8>>(entity).unwrap();
+        assert_eq!(data.content, Some([(1, vec![])].into()));
     }
 }
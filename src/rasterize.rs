@@ -0,0 +1,300 @@
+use bevy::{
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+
+use crate::{layout::*, ninepatch::NinePatch, NinePatchError};
+
+/// How the texture is sampled when rasterizing a nine-patch
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NinePatchSampling {
+    /// Use the closest pixel of the texture
+    #[default]
+    Nearest,
+    /// Interpolate between the four closest pixels of the patch. Only supported for textures with 8
+    /// bits per channel
+    Bilinear,
+}
+
+impl<T: Clone + Send + Sync + Eq + std::hash::Hash + 'static> NinePatch<T> {
+    /// Draw the nine-patch on the CPU to a new image of `size` pixels, with the format and sampler
+    /// of its original texture. Patches are sized and tiled as in a 9-Patch UI element, with
+    /// viewport units relative to `viewport_size`, in pixels. Parts of the image not covered by a patch are left
+    /// transparent.
+    ///
+    /// ```rust
+    /// # use bevy::prelude::*;
+    /// # use bevy_ninepatch::*;
+    /// fn bake_panel(
+    ///     texture: &Handle<Image>,
+    ///     textures: &mut Assets<Image>,
+    /// ) -> Result<Handle<Image>, NinePatchError> {
+    ///     let nine_patch = NinePatchBuilder::<()>::by_margins(20, 20, 20, 20)
+    ///         .apply_single_node(texture, textures)?;
+    ///     let panel = nine_patch.rasterize(
+    ///         textures,
+    ///         UVec2::new(300, 200),
+    ///         Vec2::new(1280., 720.),
+    ///         NinePatchSampling::Bilinear,
+    ///     )?;
+    ///     Ok(textures.add(panel))
+    /// }
+    /// ```
+    pub fn rasterize(
+        &self,
+        textures: &Assets<Image>,
+        size: UVec2,
+        viewport_size: Vec2,
+        sampling: NinePatchSampling,
+    ) -> Result<Image, NinePatchError> {
+        let texture = textures
+            .get(&self.texture)
+            .ok_or(NinePatchError::MissingTexture)?;
+        let texture_size = texture.texture_descriptor.size;
+        let format = texture.texture_descriptor.format;
+        let pixel_size = match format.block_size(None) {
+            Some(block_size) if format.block_dimensions() == (1, 1) => block_size as usize,
+            _ => return Err(NinePatchError::UnsupportedTextureFormat(format)),
+        };
+        // channels are interpolated byte by byte
+        let is_8_bits = matches!(
+            format,
+            TextureFormat::R8Unorm
+                | TextureFormat::Rg8Unorm
+                | TextureFormat::Rgba8Unorm
+                | TextureFormat::Rgba8UnormSrgb
+                | TextureFormat::Bgra8Unorm
+                | TextureFormat::Bgra8UnormSrgb
+        );
+        if sampling == NinePatchSampling::Bilinear && !is_8_bits {
+            return Err(NinePatchError::UnsupportedTextureFormat(format));
+        }
+
+        let target_size = size.as_vec2();
+        let rects = layout(&self.patches, texture_size, target_size, viewport_size)?;
+        let quads = self
+            .patches
+            .iter()
+            .flatten()
+            .zip(rects.into_iter().flatten())
+            .enumerate()
+            .flat_map(|(index, (patch, rect))| patch_quads(index, patch.stretch, rect));
+
+        let width = texture_size.width as usize;
+        let mut data = vec![0; size.x as usize * size.y as usize * pixel_size];
+        let mut pixel = vec![0.; pixel_size];
+        for quad in quads {
+            if quad.source.is_empty() || quad.target.is_empty() {
+                continue;
+            }
+            // pixels whose center is in the target rectangle
+            let start = (quad.target.min - 0.5).ceil().max(Vec2::ZERO).as_uvec2();
+            let end = (quad.target.max - 0.5).ceil().min(target_size).as_uvec2();
+            for y in start.y..end.y {
+                for x in start.x..end.x {
+                    let mut position = (Vec2::new(x as f32, y as f32) + 0.5 - quad.target.min)
+                        / quad.target.size();
+                    if quad.flip_x {
+                        position.x = 1. - position.x;
+                    }
+                    if quad.flip_y {
+                        position.y = 1. - position.y;
+                    }
+                    let source = quad.source.min + position * quad.source.size();
+                    // samples stay in the patch, so that neighbour patches don't bleed in
+                    let min = quad.source.min.floor();
+                    let max = quad.source.max.ceil() - 1.;
+                    match sampling {
+                        NinePatchSampling::Nearest => {
+                            let sample = source.floor().clamp(min, max);
+                            let offset =
+                                (sample.y as usize * width + sample.x as usize) * pixel_size;
+                            for (value, byte) in pixel
+                                .iter_mut()
+                                .zip(&texture.data[offset..offset + pixel_size])
+                            {
+                                *value = *byte as f32;
+                            }
+                        }
+                        NinePatchSampling::Bilinear => {
+                            let source = (source - 0.5).clamp(min, max);
+                            let low = source.floor();
+                            let high = (low + 1.).min(max);
+                            let weight = source - low;
+                            pixel.iter_mut().for_each(|value| *value = 0.);
+                            for (sample, sample_weight) in [
+                                (low, (1. - weight.x) * (1. - weight.y)),
+                                (Vec2::new(high.x, low.y), weight.x * (1. - weight.y)),
+                                (Vec2::new(low.x, high.y), (1. - weight.x) * weight.y),
+                                (high, weight.x * weight.y),
+                            ] {
+                                let offset =
+                                    (sample.y as usize * width + sample.x as usize) * pixel_size;
+                                for (value, byte) in pixel
+                                    .iter_mut()
+                                    .zip(&texture.data[offset..offset + pixel_size])
+                                {
+                                    *value += *byte as f32 * sample_weight;
+                                }
+                            }
+                        }
+                    }
+                    let offset = (y as usize * size.x as usize + x as usize) * pixel_size;
+                    for (byte, value) in data[offset..offset + pixel_size].iter_mut().zip(&pixel) {
+                        *byte = value.round() as u8;
+                    }
+                }
+            }
+        }
+
+        let mut image = Image::new(
+            Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            format,
+        );
+        image.sampler_descriptor = texture.sampler_descriptor.clone();
+        Ok(image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_utils::{app, texture},
+        NinePatchBuilder, Patch, PatchStretch, Size, StretchMode,
+    };
+
+    /// Nine-patch with a single patch filling its target size
+    fn single_patch(width: i32, height: i32, stretch: PatchStretch) -> NinePatchBuilder<()> {
+        NinePatchBuilder::from_patches(vec![vec![Patch {
+            original_size: IVec2::new(width, height),
+            target_size: Size::new(Val::Auto, Val::Auto),
+            content: None,
+            stretch,
+        }]])
+    }
+
+    fn rasterize(
+        nine_patch: &NinePatchBuilder<()>,
+        texture: Image,
+        size: UVec2,
+        sampling: NinePatchSampling,
+    ) -> Result<Vec<u8>, NinePatchError> {
+        let mut app = app();
+        let mut textures = app.world.resource_mut::<Assets<Image>>();
+        let handle = textures.add(texture);
+        let nine_patch = nine_patch.apply_single_node(&handle, &textures)?;
+        let image = nine_patch.rasterize(&textures, size, Vec2::ZERO, sampling)?;
+        assert_eq!(image.texture_descriptor.size.width, size.x);
+        assert_eq!(image.texture_descriptor.size.height, size.y);
+        Ok(image.data)
+    }
+
+    #[test]
+    fn stretch_margins() {
+        let texture = texture(3, 3, (0..9).collect(), TextureFormat::R8Unorm);
+        let nine_patch = NinePatchBuilder::by_margins(1, 1, 1, 1);
+        let data = rasterize(
+            &nine_patch,
+            texture,
+            UVec2::new(5, 4),
+            NinePatchSampling::Nearest,
+        );
+        #[rustfmt::skip]
+        assert_eq!(data, Ok(vec![
+            0, 1, 1, 1, 2,
+            3, 4, 4, 4, 5,
+            3, 4, 4, 4, 5,
+            6, 7, 7, 7, 8,
+        ]));
+    }
+
+    #[test]
+    fn uncovered_pixels_are_transparent() {
+        let texture = texture(2, 1, vec![255; 8], TextureFormat::Rgba8UnormSrgb);
+        let mut nine_patch = single_patch(2, 1, PatchStretch::default());
+        nine_patch.patches[0][0].target_size = Size::new(Val::Px(0.), Val::Px(0.));
+        let data = rasterize(
+            &nine_patch,
+            texture,
+            UVec2::new(3, 2),
+            NinePatchSampling::Nearest,
+        )
+        .unwrap();
+        let alpha: Vec<u8> = data.chunks(4).map(|pixel| pixel[3]).collect();
+        assert_eq!(alpha, vec![255, 255, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn tiled() {
+        let texture = texture(4, 1, vec![10, 20, 30, 40], TextureFormat::R8Unorm);
+        let nine_patch = single_patch(4, 1, PatchStretch::all(StretchMode::Tile));
+        let data = rasterize(
+            &nine_patch,
+            texture,
+            UVec2::new(10, 1),
+            NinePatchSampling::Nearest,
+        );
+        assert_eq!(data, Ok(vec![10, 20, 30, 40, 10, 20, 30, 40, 10, 20]));
+    }
+
+    #[test]
+    fn mirrored() {
+        let row = texture(4, 1, vec![10, 20, 30, 40], TextureFormat::R8Unorm);
+        let nine_patch = single_patch(4, 1, PatchStretch::all(StretchMode::Mirror));
+        let data = rasterize(
+            &nine_patch,
+            row,
+            UVec2::new(10, 1),
+            NinePatchSampling::Nearest,
+        );
+        assert_eq!(data, Ok(vec![10, 20, 30, 40, 40, 30, 20, 10, 10, 20]));
+
+        let column = texture(1, 2, vec![1, 2], TextureFormat::R8Unorm);
+        let nine_patch = single_patch(1, 2, PatchStretch::all(StretchMode::Mirror));
+        let data = rasterize(
+            &nine_patch,
+            column,
+            UVec2::new(1, 5),
+            NinePatchSampling::Nearest,
+        );
+        assert_eq!(data, Ok(vec![1, 2, 2, 1, 1]));
+    }
+
+    #[test]
+    fn bilinear() {
+        let texture = texture(2, 1, vec![0, 100], TextureFormat::R8Unorm);
+        let nine_patch = single_patch(2, 1, PatchStretch::default());
+        let data = rasterize(
+            &nine_patch,
+            texture,
+            UVec2::new(4, 1),
+            NinePatchSampling::Bilinear,
+        );
+        assert_eq!(data, Ok(vec![0, 25, 75, 100]));
+    }
+
+    #[test]
+    fn unsupported_format() {
+        let texture = texture(1, 1, vec![0; 4], TextureFormat::R32Float);
+        let nine_patch = single_patch(1, 1, PatchStretch::default());
+        let data = rasterize(
+            &nine_patch,
+            texture,
+            UVec2::new(2, 2),
+            NinePatchSampling::Bilinear,
+        );
+        assert_eq!(
+            data,
+            Err(NinePatchError::UnsupportedTextureFormat(
+                TextureFormat::R32Float
+            ))
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use bevy::{
+    asset::{AssetLoader, AssetPath, BoxedFuture, LoadContext, LoadedAsset},
+    reflect::TypePath,
+};
+use serde::Deserialize;
+
+use crate::ninepatch::*;
+
+/// Layout of the patches in a `.ninepatch.ron` file
+#[derive(Deserialize)]
+enum NinePatchLayout<T: Clone + Send + Sync + 'static> {
+    /// Same as `NinePatchBuilder::by_margins_with_content`, with an optional `PatchStretch` for all
+    /// patches
+    Margins {
+        top: u32,
+        bottom: u32,
+        left: u32,
+        right: u32,
+        content: T,
+        #[serde(default)]
+        stretch: PatchStretch,
+    },
+    /// Same as `NinePatchBuilder::from_patches`
+    Patches(Vec<Vec<Patch<T>>>),
+}
+
+/// Content of a `.ninepatch.ron` file
+#[derive(Deserialize)]
+struct NinePatchDescription<T: Clone + Send + Sync + 'static> {
+    /// Path to the texture, relative to the `.ninepatch.ron` file
+    #[serde(default)]
+    texture: Option<String>,
+    layout: NinePatchLayout<T>,
+    /// How patches are rendered. If not set, the mode from `NinePatchSettings` is used
+    #[serde(default)]
+    render_mode: Option<NinePatchRenderMode>,
+}
+
+/// Asset loader for `NinePatchBuilder` described in `.ninepatch.ron` files, available with the
+/// `ron` feature.
+///
+/// This loader is added by `NinePatchPlugin<()>`, and by `NinePatchPlugin<T>` with
+/// `NinePatchPlugin::with_ron_loader`. Only one loader can handle the extension, so
+/// `.ninepatch.ron` files can only be loaded for one content type `T`.
+///
+/// ```ron
+/// (
+///     texture: Some("glassPanel_corners.png"),
+///     layout: Margins(top: 20, bottom: 20, left: 20, right: 20, content: ()),
+/// )
+/// ```
+///
+/// Patches can be tiled instead of stretched with
+/// `Margins(..., stretch: (horizontal: Tile, vertical: Stretch))`, and the render mode can be set
+/// with `render_mode: Some(TextureAtlas)`.
+///
+/// The layout can also be given patch by patch:
+///
+/// ```ron
+/// (
+///     layout: Patches([
+///         [
+///             (original_size: (10, 10), target_size: (width: Px(0.), height: Px(0.))),
+///             (original_size: (-10, 10), target_size: (width: Auto, height: Px(0.))),
+///         ],
+///         [
+///             (original_size: (10, -10), target_size: (width: Px(0.), height: Auto)),
+///             (
+///                 original_size: (-10, -10),
+///                 target_size: (width: Auto, height: Auto),
+///                 content: Some(()),
+///             ),
+///         ],
+///     ]),
+/// )
+/// ```
+///
+/// The texture is loaded as a dependency of the `NinePatchBuilder`, and used by `NinePatchData`
+/// that don't set their own texture.
+#[derive(Debug, Clone, Copy)]
+pub struct NinePatchRonLoader<T> {
+    marker: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<T> Default for NinePatchRonLoader<T> {
+    fn default() -> Self {
+        NinePatchRonLoader {
+            marker: Default::default(),
+        }
+    }
+}
+
+impl<T> AssetLoader for NinePatchRonLoader<T>
+where
+    T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static,
+    T: serde::de::DeserializeOwned,
+{
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let description: NinePatchDescription<T> = ron::de::from_bytes(bytes)?;
+            let mut nine_patch = match description.layout {
+                NinePatchLayout::Margins {
+                    top,
+                    bottom,
+                    left,
+                    right,
+                    content,
+                    stretch,
+                } => NinePatchBuilder::by_margins_with_content(top, bottom, left, right, content)
+                    .with_stretch(stretch),
+                NinePatchLayout::Patches(patches) => NinePatchBuilder::from_patches(patches),
+            };
+            nine_patch.render_mode = description.render_mode;
+            let texture_path = description.texture.map(|texture| {
+                let path = load_context
+                    .path()
+                    .parent()
+                    .map(|parent| parent.join(&texture))
+                    .unwrap_or_else(|| texture.into());
+                AssetPath::new(path, None)
+            });
+            if let Some(texture_path) = &texture_path {
+                nine_patch.texture = Some(load_context.get_handle(texture_path.get_id()));
+            }
+            let mut asset = LoadedAsset::new(nine_patch);
+            if let Some(texture_path) = texture_path {
+                asset.add_dependency(texture_path);
+            }
+            load_context.set_default_asset(asset);
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ninepatch.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::{asset::LoadState, prelude::*};
+
+    /// Load `files` from a new asset folder with the `NinePatchPlugin`, and return the
+    /// `NinePatchBuilder` loaded from `path`
+    fn load(folder: &str, files: &[(&str, &str)], path: &str) -> NinePatchBuilder {
+        let folder = std::env::temp_dir().join(folder);
+        for (file, content) in files {
+            let file = folder.join(file);
+            std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+            std::fs::write(file, content).unwrap();
+        }
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin {
+                asset_folder: folder.to_string_lossy().into_owned(),
+                ..Default::default()
+            },
+        ))
+        .add_asset::<Image>()
+        .add_plugins(crate::NinePatchPlugin::<()>::default());
+        let handle: Handle<NinePatchBuilder> = app.world.resource::<AssetServer>().load(path);
+        for _ in 0..100 {
+            app.update();
+            let mut nine_patches = app.world.resource_mut::<Assets<NinePatchBuilder>>();
+            if let Some(nine_patch) = nine_patches.remove(&handle) {
+                return nine_patch;
+            }
+            let load_state = app.world.resource::<AssetServer>().get_load_state(&handle);
+            assert_ne!(load_state, LoadState::Failed);
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        panic!("{path} was not loaded");
+    }
+
+    #[test]
+    fn margins() {
+        let nine_patch = load(
+            "bevy_ninepatch_ron_margins",
+            &[(
+                "ui/panel.ninepatch.ron",
+                "(
+                    texture: Some(\"panel.png\"),
+                    layout: Margins(
+                        top: 1, bottom: 2, left: 3, right: 4, content: (),
+                        stretch: (horizontal: Tile, vertical: Mirror),
+                    ),
+                    render_mode: Some(TextureAtlas),
+                )",
+            )],
+            "ui/panel.ninepatch.ron",
+        );
+        let mut expected = NinePatchBuilder::by_margins(1, 2, 3, 4)
+            .with_stretch(PatchStretch::new(StretchMode::Tile, StretchMode::Mirror));
+        expected.render_mode = Some(NinePatchRenderMode::TextureAtlas);
+        assert_eq!(nine_patch.patches, expected.patches);
+        assert_eq!(nine_patch.render_mode, expected.render_mode);
+        // the texture is relative to the `.ninepatch.ron` file
+        let texture = AssetPath::new(std::path::Path::new("ui").join("panel.png"), None);
+        assert_eq!(
+            nine_patch.texture.map(|texture| texture.id()),
+            Some(texture.get_id().into())
+        );
+    }
+
+    #[test]
+    fn patches() {
+        let nine_patch = load(
+            "bevy_ninepatch_ron_patches",
+            &[(
+                "panel.ninepatch.ron",
+                "(
+                    layout: Patches([[
+                        (original_size: (10, 10), target_size: (width: Px(0.), height: Px(0.))),
+                        (
+                            original_size: (-10, 10),
+                            target_size: (width: Auto, height: Px(0.)),
+                            content: Some(()),
+                        ),
+                    ]]),
+                )",
+            )],
+            "panel.ninepatch.ron",
+        );
+        let patch = |original_size, width, content| Patch {
+            original_size,
+            target_size: Size::new(width, Val::Px(0.)),
+            content,
+            stretch: PatchStretch::default(),
+        };
+        assert_eq!(
+            nine_patch.patches,
+            vec![vec![
+                patch(IVec2::new(10, 10), Val::Px(0.), None),
+                patch(IVec2::new(-10, 10), Val::Auto, Some(())),
+            ]]
+        );
+        assert_eq!(nine_patch.render_mode, None);
+        assert!(nine_patch.texture.is_none());
+    }
+}
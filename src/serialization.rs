@@ -0,0 +1,100 @@
+use bevy::reflect::TypePath;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::ninepatch::*;
+
+/// `IVec2` is (de)serialized as a tuple `(x, y)` without requiring the `serialize` feature of Bevy
+pub(crate) mod ivec2 {
+    use bevy::math::IVec2;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub(crate) fn serialize<S: Serializer>(
+        value: &IVec2,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        (value.x, value.y).serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<IVec2, D::Error> {
+        let (x, y) = <(i32, i32)>::deserialize(deserializer)?;
+        Ok(IVec2::new(x, y))
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename = "NinePatchBuilder")]
+struct SerializeBuilder<'a, T: Clone + Send + Sync + Serialize + 'static> {
+    patches: &'a Vec<Vec<Patch<T>>>,
+    render_mode: &'a Option<NinePatchRenderMode>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "NinePatchBuilder")]
+struct DeserializeBuilder<T: Clone + Send + Sync + 'static> {
+    patches: Vec<Vec<Patch<T>>>,
+    #[serde(default)]
+    render_mode: Option<NinePatchRenderMode>,
+}
+
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_ninepatch::*;
+/// let nine_patch = NinePatchBuilder::by_margins_with_content(5, 10, 6, 6, 42)
+///     .with_render_mode(NinePatchRenderMode::TextureAtlas);
+///
+/// let serialized = ron::to_string(&nine_patch).unwrap();
+/// let deserialized: NinePatchBuilder<i32> = ron::from_str(&serialized).unwrap();
+///
+/// assert_eq!(deserialized.patches, nine_patch.patches);
+/// assert_eq!(deserialized.patches[1][1].content, Some(42));
+/// assert_eq!(deserialized.render_mode, Some(NinePatchRenderMode::TextureAtlas));
+/// ```
+impl<T> Serialize for NinePatchBuilder<T>
+where
+    T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + Serialize + 'static,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializeBuilder {
+            patches: &self.patches,
+            render_mode: &self.render_mode,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_ninepatch::*;
+/// let nine_patch: NinePatchBuilder<()> = ron::from_str(
+///     "(patches: [[
+///         (original_size: (10, 10), target_size: (width: Px(0.), height: Px(0.))),
+///         (original_size: (-10, 10), target_size: (width: Auto, height: Px(0.)), content: Some(())),
+///     ]])",
+/// )
+/// .unwrap();
+///
+/// assert_eq!(nine_patch.patches[0][1].original_size, IVec2::new(-10, 10));
+/// assert_eq!(nine_patch.patches[0][1].target_size, Size::new(Val::Auto, Val::Px(0.)));
+/// assert_eq!(nine_patch.patches[0][1].content, Some(()));
+/// assert_eq!(nine_patch.render_mode, None);
+/// assert_eq!(ron::to_string(&nine_patch.patches[0][0]).unwrap(),
+///     "(original_size:(10,10),target_size:(width:Px(0.0),height:Px(0.0)),content:None,\
+///     stretch:(horizontal:Stretch,vertical:Stretch))");
+/// ```
+impl<'de, T> Deserialize<'de> for NinePatchBuilder<T>
+where
+    T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + Deserialize<'de> + 'static,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let DeserializeBuilder {
+            patches,
+            render_mode,
+        } = DeserializeBuilder::deserialize(deserializer)?;
+        Ok(NinePatchBuilder {
+            render_mode,
+            ..NinePatchBuilder::from_patches(patches)
+        })
+    }
+}
@@ -0,0 +1,438 @@
+use bevy::{
+    prelude::*,
+    reflect::TypePath,
+    render::{Extract, ExtractSchedule, RenderApp},
+    ui::{
+        CalculatedClip, ExtractedUiNode, ExtractedUiNodes, FocusPolicy, RenderUiSystem, UiScale,
+        UiStack,
+    },
+    window::PrimaryWindow,
+};
+
+use crate::{layout::*, ninepatch::*};
+
+/// Slices of a nine-patch drawn by a single UI node, added on the entity holding the
+/// `NinePatchData`
+#[derive(Debug, Clone, Component)]
+pub(crate) struct NinePatchSlices {
+    /// Texture sliced when rendering
+    texture: Handle<Image>,
+    /// Size of the texture, in pixels
+    texture_size: Vec2,
+    /// Size of the node and of the viewport the slices were computed for
+    size: Option<(Vec2, Vec2)>,
+    /// Quads drawing the patches
+    quads: Vec<SliceQuad>,
+    /// Color of each patch
+    tints: Vec<Color>,
+}
+
+impl NinePatchSlices {
+    /// Slice another texture of the same size
+    pub(crate) fn set_texture(&mut self, texture: &Handle<Image>) {
+        self.texture = texture.clone_weak();
+    }
+
+    /// Set the color of each patch
+    pub(crate) fn set_tints(&mut self, tints: Vec<Color>) {
+        self.tints = tints;
+    }
+}
+
+impl<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> NinePatch<T> {
+    /// Add the nine-patch as a single node on `parent`. Content zones are laid out by an invisible
+    /// child mirroring the rows and patches of the other render modes, so that they are placed by
+    /// the UI layout in the same frame
+    pub(crate) fn add_single_node(
+        &self,
+        commands: &mut Commands,
+        parent: Entity,
+        style: &Style,
+        contents: &Option<std::collections::HashMap<T, Vec<Entity>>>,
+        nine_patch: &Handle<NinePatchBuilder<T>>,
+        texture: &Handle<Image>,
+    ) -> NinePatchParts<T> {
+        let mut content_zones = std::collections::HashMap::new();
+        let zones = commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        left: Val::Px(0.),
+                        top: Val::Px(0.),
+                        width: Val::Percent(100.),
+                        height: Val::Percent(100.),
+                        flex_direction: FlexDirection::Column,
+                        ..Default::default()
+                    },
+                    background_color: BackgroundColor(Color::NONE),
+                    focus_policy: FocusPolicy::Pass,
+                    ..Default::default()
+                },
+                Name::new("Nine-patch zones"),
+                GeneratedNode { nine_patch: parent },
+            ))
+            .id();
+        for (row_index, row) in self.patches.iter().enumerate() {
+            let (height, growth, basis) = row
+                .first()
+                .map(|patch| {
+                    let original = to_height(patch.original_size, self.texture_size);
+                    let (height, growth) = flex_size(patch.target_size.height, original);
+                    (height, growth, original)
+                })
+                .unwrap_or((Val::Px(0.), 0., 0));
+            let row_entity = commands
+                .spawn((
+                    NodeBundle {
+                        style: flex_style(Val::Percent(100.), height, growth, basis),
+                        background_color: BackgroundColor(Color::NONE),
+                        focus_policy: FocusPolicy::Pass,
+                        ..Default::default()
+                    },
+                    NinePatchRow { row: row_index },
+                    Name::new(format!("Nine-patch row {row_index}")),
+                ))
+                .set_parent(zones)
+                .id();
+            // only the rows holding a content zone need their patches
+            if row.iter().all(|patch| patch.content.is_none()) {
+                continue;
+            }
+            for (column_index, patch) in row.iter().enumerate() {
+                let original = to_width(patch.original_size, self.texture_size);
+                let (width, growth) = flex_size(patch.target_size.width, original);
+                let mut zone = commands.spawn((
+                    NodeBundle {
+                        style: flex_style(width, Val::Auto, growth, original),
+                        background_color: BackgroundColor(Color::NONE),
+                        focus_policy: FocusPolicy::Pass,
+                        ..Default::default()
+                    },
+                    NinePatchPart::for_patch(row_index, column_index, patch),
+                ));
+                zone.set_parent(row_entity);
+                let Some(content_part) = patch.content.as_ref() else {
+                    continue;
+                };
+                zone.insert(NinePatchContent {
+                    content: content_part.clone(),
+                    loaded: false,
+                    parent,
+                });
+                if let Some(content_entities) = contents.as_ref().and_then(|m| m.get(content_part))
+                {
+                    zone.push_children(content_entities);
+                }
+                content_zones.insert(content_part.clone(), zone.id());
+            }
+        }
+        insert_node(commands, parent, style.clone());
+        commands
+            .entity(parent)
+            .insert((
+                NinePatchSlices {
+                    texture: texture.clone_weak(),
+                    texture_size: Vec2::new(
+                        self.texture_size.width as f32,
+                        self.texture_size.height as f32,
+                    ),
+                    size: None,
+                    quads: vec![],
+                    tints: vec![],
+                },
+                NinePatchZones {
+                    zones: content_zones,
+                },
+            ))
+            .add_child(zones);
+        NinePatchParts {
+            rows: vec![zones],
+            patches: vec![],
+            nine_patch: nine_patch.clone_weak(),
+            texture: texture.clone_weak(),
+            render_mode: NinePatchRenderMode::SingleNode,
+            texture_size: self.texture_size,
+            patches_layout: self.patches.clone(),
+            contents: contents.clone().unwrap_or_default(),
+        }
+    }
+}
+
+/// Style of a row or of a patch laying out the content zones. Growing nodes start from the
+/// original size of their patch, as the images of the other render modes do
+fn flex_style(width: Val, height: Val, growth: f32, basis: u32) -> Style {
+    Style {
+        width,
+        height,
+        flex_direction: FlexDirection::Row,
+        flex_grow: growth,
+        flex_shrink: growth,
+        flex_basis: if growth > 0. {
+            Val::Px(basis as f32)
+        } else {
+            Val::Auto
+        },
+        ..Default::default()
+    }
+}
+
+/// Plugin drawing the nine-patches rendered as a single node, shared by all `NinePatchPlugin`
+pub(crate) struct SingleNodePlugin;
+
+impl Plugin for SingleNodePlugin {
+    fn build(&self, _app: &mut App) {}
+
+    // slices are added to the UI nodes extracted by `UiPlugin`, which may be added after this
+    // plugin, so they are registered once all plugins are built
+    fn finish(&self, app: &mut App) {
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        if !render_app.world.contains_resource::<ExtractedUiNodes>() {
+            warn!("UiPlugin is missing, nine-patches rendered as a single node won't be drawn");
+            return;
+        }
+        render_app.add_systems(
+            ExtractSchedule,
+            extract_slices.after(RenderUiSystem::ExtractAtlasNode),
+        );
+    }
+}
+
+/// Compute the slices of the nine-patches whose node has been resized
+pub(crate) fn update_slices<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static>(
+    mut nine_patches: Query<(&Node, &NinePatchParts<T>, &mut NinePatchSlices)>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    ui_scale: Option<Res<UiScale>>,
+) {
+    // viewport units are relative to the size of the primary window, in the unit of `Node`
+    let ui_scale = ui_scale.map(|ui_scale| ui_scale.scale as f32).unwrap_or(1.);
+    let viewport_size = windows
+        .get_single()
+        .map(|window| Vec2::new(window.width(), window.height()) / ui_scale)
+        .unwrap_or_default();
+    for (node, parts, mut slices) in nine_patches.iter_mut() {
+        let size = node.size();
+        if slices.size == Some((size, viewport_size)) {
+            continue;
+        }
+        // target sizes have been checked when applying the `NinePatchBuilder`
+        let Ok(rects) = layout(
+            &parts.patches_layout,
+            parts.texture_size,
+            size,
+            viewport_size,
+        ) else {
+            continue;
+        };
+        slices.quads = parts
+            .patches_layout
+            .iter()
+            .flatten()
+            .zip(rects.into_iter().flatten())
+            .enumerate()
+            .flat_map(|(index, (patch, rect))| patch_quads(index, patch.stretch, rect))
+            .collect();
+        slices.size = Some((size, viewport_size));
+    }
+}
+
+/// Add a UI quad for each slice of the nine-patches rendered as a single node
+#[allow(clippy::type_complexity)]
+fn extract_slices(
+    mut extracted_uinodes: ResMut<ExtractedUiNodes>,
+    images: Extract<Res<Assets<Image>>>,
+    ui_stack: Extract<Res<UiStack>>,
+    nine_patches: Extract<
+        Query<(
+            &Node,
+            &GlobalTransform,
+            &ComputedVisibility,
+            Option<&CalculatedClip>,
+            &NinePatchSlices,
+        )>,
+    >,
+) {
+    for (stack_index, entity) in ui_stack.uinodes.iter().enumerate() {
+        let Ok((node, transform, visibility, clip, slices)) = nine_patches.get(*entity) else {
+            continue;
+        };
+        if !visibility.is_visible() || !images.contains(&slices.texture) {
+            continue;
+        }
+        let center = transform.translation().truncate();
+        let transform = transform.compute_matrix();
+        for quad in &slices.quads {
+            let target = Rect {
+                min: quad.target.min + center - node.size() / 2.,
+                max: quad.target.max + center - node.size() / 2.,
+            };
+            let Some((source, target)) = clip_slice(quad, target, clip.map(|clip| clip.clip))
+            else {
+                continue;
+            };
+            // the quad has the size of the source rectangle, and is scaled to fill its target
+            let scale = target.size() / source.size();
+            extracted_uinodes.uinodes.push(ExtractedUiNode {
+                stack_index,
+                transform: transform
+                    * Mat4::from_translation((target.center() - center).extend(0.))
+                    * Mat4::from_scale(scale.extend(1.)),
+                color: slices
+                    .tints
+                    .get(quad.patch)
+                    .copied()
+                    .unwrap_or(Color::WHITE),
+                rect: source,
+                image: slices.texture.clone_weak(),
+                atlas_size: Some(slices.texture_size),
+                // the UI renderer can't clip scaled quads, so they are clipped here
+                clip: None,
+                flip_x: quad.flip_x,
+                flip_y: quad.flip_y,
+            });
+        }
+    }
+}
+
+/// Clip the `target` rectangle of a quad, in UI coordinates, and the part of its source
+/// rectangle it still draws. Returns `None` if nothing is left to draw
+fn clip_slice(quad: &SliceQuad, target: Rect, clip: Option<Rect>) -> Option<(Rect, Rect)> {
+    let clipped = clip.map_or(target, |clip| target.intersect(clip));
+    if quad.source.is_empty() || clipped.width() <= 0. || clipped.height() <= 0. {
+        return None;
+    }
+    // position of the clipped target in the quad, from 0 to 1, mirrored when the quad is flipped
+    let mut min = (clipped.min - target.min) / target.size();
+    let mut max = (clipped.max - target.min) / target.size();
+    if quad.flip_x {
+        (min.x, max.x) = (1. - max.x, 1. - min.x);
+    }
+    if quad.flip_y {
+        (min.y, max.y) = (1. - max.y, 1. - min.y);
+    }
+    let source = Rect {
+        min: quad.source.min + min * quad.source.size(),
+        max: quad.source.min + max * quad.source.size(),
+    };
+    (!source.is_empty()).then_some((source, clipped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils::*, NinePatchBundle, NinePatchData, NinePatchPlugin};
+    use bevy::{reflect::Struct, render::render_resource::TextureFormat};
+
+    /// Set the size of a node as the UI layout does
+    fn set_size(node: &mut Node, size: Vec2) {
+        *node
+            .field_mut("calculated_size")
+            .and_then(|field| field.downcast_mut::<Vec2>())
+            .unwrap() = size;
+    }
+
+    fn quad(source: Rect, target: Rect) -> (Rect, Rect, bool, bool) {
+        (source, target, false, false)
+    }
+
+    #[test]
+    fn slices_follow_node_size() {
+        let mut app = app();
+        app.add_plugins(NinePatchPlugin::<()>::default());
+        let texture = app.world.resource_mut::<Assets<Image>>().add(texture(
+            30,
+            30,
+            vec![255; 30 * 30 * 4],
+            TextureFormat::Rgba8UnormSrgb,
+        ));
+        let nine_patch = app.world.resource_mut::<Assets<NinePatchBuilder>>().add(
+            NinePatchBuilder::by_margins(10, 10, 10, 10)
+                .with_render_mode(NinePatchRenderMode::SingleNode),
+        );
+        let entity = app
+            .world
+            .spawn(NinePatchBundle::<()> {
+                nine_patch_data: NinePatchData {
+                    texture,
+                    nine_patch,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .id();
+        let quads = |app: &mut App, size: Vec2| {
+            set_size(&mut app.world.get_mut::<Node>(entity).unwrap(), size);
+            app.update();
+            let slices = app.world.get::<NinePatchSlices>(entity).unwrap();
+            slices
+                .quads
+                .iter()
+                .map(|quad| (quad.source, quad.target, quad.flip_x, quad.flip_y))
+                .collect::<Vec<_>>()
+        };
+        app.update();
+        // corners keep their size, the other patches fill the rest of the node
+        let expected = |width: f32, height: f32| {
+            let columns = [
+                (0., 10., 0., 10.),
+                (10., 20., 10., width - 10.),
+                (20., 30., width - 10., width),
+            ];
+            let rows = [
+                (0., 10., 0., 10.),
+                (10., 20., 10., height - 10.),
+                (20., 30., height - 10., height),
+            ];
+            rows.iter()
+                .flat_map(|&(source_top, source_bottom, top, bottom)| {
+                    columns
+                        .iter()
+                        .map(move |&(source_left, source_right, left, right)| {
+                            quad(
+                                Rect::new(source_left, source_top, source_right, source_bottom),
+                                Rect::new(left, top, right, bottom),
+                            )
+                        })
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(quads(&mut app, Vec2::new(100., 50.)), expected(100., 50.));
+        assert_eq!(quads(&mut app, Vec2::new(40., 60.)), expected(40., 60.));
+    }
+
+    #[test]
+    fn clip_slices() {
+        let quad = SliceQuad {
+            patch: 0,
+            source: Rect::new(0., 0., 10., 10.),
+            target: Rect::new(0., 0., 20., 20.),
+            flip_x: false,
+            flip_y: false,
+        };
+        let target = Rect::new(100., 100., 120., 120.);
+        assert_eq!(clip_slice(&quad, target, None), Some((quad.source, target)));
+        // the part of the texture drawn is cut like the target
+        let clip = Rect::new(110., 0., 200., 105.);
+        let clipped = Rect::new(110., 100., 120., 105.);
+        assert_eq!(
+            clip_slice(&quad, target, Some(clip)),
+            Some((Rect::new(5., 0., 10., 2.5), clipped))
+        );
+        let flipped = SliceQuad {
+            flip_x: true,
+            flip_y: true,
+            ..quad
+        };
+        assert_eq!(
+            clip_slice(&flipped, target, Some(clip)),
+            Some((Rect::new(0., 7.5, 5., 10.), clipped))
+        );
+        // quads outside of the clipping rectangle are not drawn
+        assert_eq!(
+            clip_slice(&quad, target, Some(Rect::new(0., 0., 100., 100.))),
+            None
+        );
+    }
+}
@@ -0,0 +1,324 @@
+use bevy::{asset::HandleId, prelude::*, reflect::TypePath, window::PrimaryWindow};
+
+use crate::{layout::*, ninepatch::*, NinePatchErrorEvent};
+
+/// A nine-patch displayed in world space at `size`, centered on its `Transform`. The patches are
+/// drawn by child sprites, sized and tiled like the patches of a 9-Patch UI element.
+///
+/// Viewport units are relative to the primary window, in world units. Content zones are not
+/// spawned, children can be added to the entity instead.
+#[derive(Debug, Clone, Component)]
+pub struct NinePatchSprite<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> {
+    /// Handle of the texture. If not set, the texture of the `NinePatchBuilder` is used
+    pub texture: Handle<Image>,
+    /// Handle to the `NinePatchBuilder`
+    pub nine_patch: Handle<NinePatchBuilder<T>>,
+    /// Size of the nine-patch, in world units
+    pub size: Vec2,
+    /// Color multiplied with the texture of all patches
+    pub color: Color,
+}
+
+impl<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> Default
+    for NinePatchSprite<T>
+{
+    fn default() -> Self {
+        NinePatchSprite {
+            texture: Default::default(),
+            nine_patch: Default::default(),
+            size: Vec2::ZERO,
+            color: Color::WHITE,
+        }
+    }
+}
+
+/// Component bundle to place a `NinePatchSprite` in the world
+#[derive(Bundle)]
+pub struct NinePatchSpriteBundle<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static>
+{
+    /// The nine-patch to display
+    pub sprite: NinePatchSprite<T>,
+    /// Transform of the center of the nine-patch
+    pub transform: Transform,
+    /// Global transform - should be set automatically by bevy's systems
+    pub global_transform: GlobalTransform,
+    /// Visibility of the nine-patch
+    pub visibility: Visibility,
+    /// Computed visibility - should be set automatically by bevy's systems
+    pub computed_visibility: ComputedVisibility,
+}
+
+impl<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> Default
+    for NinePatchSpriteBundle<T>
+{
+    fn default() -> Self {
+        NinePatchSpriteBundle {
+            sprite: Default::default(),
+            transform: Default::default(),
+            global_transform: Default::default(),
+            visibility: Default::default(),
+            computed_visibility: Default::default(),
+        }
+    }
+}
+
+/// Sprites drawing a `NinePatchSprite`, and what they were spawned for
+#[derive(Debug, Clone, Component)]
+pub(crate) struct NinePatchSpriteParts {
+    /// Texture, `NinePatchBuilder`, size and color the sprites were spawned for
+    key: (HandleId, HandleId, Vec2, Color, Vec2),
+    /// Sprite entities, children of the `NinePatchSprite` entity
+    sprites: Vec<Entity>,
+}
+
+/// Spawn the sprites of the `NinePatchSprite`s that changed, or whose assets are now loaded or
+/// have been modified
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+pub(crate) fn update_sprites<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static>(
+    mut commands: Commands,
+    nine_patches: Res<Assets<NinePatchBuilder<T>>>,
+    textures: Res<Assets<Image>>,
+    mut nine_patch_events: EventReader<AssetEvent<NinePatchBuilder<T>>>,
+    mut texture_events: EventReader<AssetEvent<Image>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut sprites: Query<(
+        Entity,
+        &NinePatchSprite<T>,
+        Option<&mut NinePatchSpriteParts>,
+    )>,
+    mut error_events: EventWriter<NinePatchErrorEvent>,
+) {
+    let viewport_size = windows
+        .get_single()
+        .map(|window| Vec2::new(window.width(), window.height()))
+        .unwrap_or_default();
+    let modified: Vec<HandleId> = nine_patch_events
+        .iter()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { handle } => Some(handle.id()),
+            _ => None,
+        })
+        .chain(texture_events.iter().filter_map(|event| match event {
+            AssetEvent::Modified { handle } => Some(handle.id()),
+            _ => None,
+        }))
+        .collect();
+    for (entity, sprite, parts) in sprites.iter_mut() {
+        let Some(nine_patch) = nine_patches.get(&sprite.nine_patch) else {
+            continue;
+        };
+        let texture_handle = match &nine_patch.texture {
+            Some(texture) if sprite.texture == Handle::default() => texture.clone(),
+            _ => sprite.texture.clone(),
+        };
+        let Some(texture) = textures.get(&texture_handle) else {
+            continue;
+        };
+        let key = (
+            texture_handle.id(),
+            sprite.nine_patch.id(),
+            sprite.size,
+            sprite.color,
+            viewport_size,
+        );
+        let is_modified =
+            modified.contains(&texture_handle.id()) || modified.contains(&sprite.nine_patch.id());
+        if let Some(parts) = &parts {
+            if parts.key == key && !is_modified {
+                continue;
+            }
+            for sprite in &parts.sprites {
+                commands.entity(*sprite).despawn_recursive();
+            }
+        }
+        let mut sprite_entities = vec![];
+        let texture_size = texture.texture_descriptor.size;
+        let result = nine_patch
+            .validate(UVec2::new(texture_size.width, texture_size.height))
+            .and_then(|_| {
+                layout(
+                    &nine_patch.patches,
+                    texture_size,
+                    sprite.size,
+                    viewport_size,
+                )
+            });
+        let rects = match result {
+            Ok(rects) => rects,
+            Err(error) => {
+                error!("could not create nine-patch sprite for {entity:?}: {error}");
+                error_events.send(NinePatchErrorEvent { entity, error });
+                vec![]
+            }
+        };
+        let quads = nine_patch
+            .patches
+            .iter()
+            .flatten()
+            .zip(rects.into_iter().flatten())
+            .enumerate()
+            .flat_map(|(index, (patch, rect))| patch_quads(index, patch.stretch, rect));
+        for quad in quads {
+            if quad.source.is_empty() || quad.target.is_empty() {
+                continue;
+            }
+            // quads are placed from the top left corner, with y going down
+            let center = quad.target.center();
+            let translation =
+                Vec2::new(center.x - sprite.size.x / 2., sprite.size.y / 2. - center.y);
+            let sprite_entity = commands
+                .spawn(SpriteBundle {
+                    sprite: Sprite {
+                        color: sprite.color,
+                        flip_x: quad.flip_x,
+                        flip_y: quad.flip_y,
+                        custom_size: Some(quad.target.size()),
+                        rect: Some(quad.source),
+                        ..Default::default()
+                    },
+                    texture: texture_handle.clone(),
+                    transform: Transform::from_translation(translation.extend(0.)),
+                    ..Default::default()
+                })
+                .id();
+            sprite_entities.push(sprite_entity);
+        }
+        commands.entity(entity).push_children(&sprite_entities);
+        let new_parts = NinePatchSpriteParts {
+            key,
+            sprites: sprite_entities,
+        };
+        match parts {
+            Some(mut parts) => *parts = new_parts,
+            None => {
+                commands.entity(entity).insert(new_parts);
+            }
+        }
+    }
+}
+
+/// Remove the sprites of the `NinePatchSprite`s that have been removed
+pub(crate) fn remove_sprites<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static>(
+    mut commands: Commands,
+    mut removed: RemovedComponents<NinePatchSprite<T>>,
+    sprites: Query<&NinePatchSpriteParts>,
+) {
+    for entity in removed.iter() {
+        let Ok(parts) = sprites.get(entity) else {
+            continue;
+        };
+        for sprite in &parts.sprites {
+            commands.entity(*sprite).despawn_recursive();
+        }
+        commands.entity(entity).remove::<NinePatchSpriteParts>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils::*, NinePatchPlugin};
+    use bevy::render::render_resource::TextureFormat;
+
+    /// Rectangle in the texture, size and translation of the sprites of `entity`
+    fn sprites(app: &App, entity: Entity) -> Vec<(Rect, Vec2, Vec2)> {
+        let children = app.world.get::<Children>(entity).unwrap();
+        children
+            .iter()
+            .map(|child| {
+                let sprite = app.world.get::<Sprite>(*child).unwrap();
+                let transform = app.world.get::<Transform>(*child).unwrap();
+                (
+                    sprite.rect.unwrap(),
+                    sprite.custom_size.unwrap(),
+                    transform.translation.truncate(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn margins() {
+        let mut app = app();
+        app.add_plugins(NinePatchPlugin::<()>::default());
+        let texture = app.world.resource_mut::<Assets<Image>>().add(texture(
+            3,
+            3,
+            vec![0; 9],
+            TextureFormat::R8Unorm,
+        ));
+        let nine_patch = app
+            .world
+            .resource_mut::<Assets<NinePatchBuilder>>()
+            .add(NinePatchBuilder::by_margins(1, 1, 1, 1));
+        let entity = app
+            .world
+            .spawn(NinePatchSpriteBundle::<()> {
+                sprite: NinePatchSprite {
+                    texture: texture.clone(),
+                    nine_patch,
+                    size: Vec2::new(5., 4.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .id();
+        app.update();
+        // left and right of each column, top and bottom of each row, in the texture, with the
+        // width or height and the center of the sprites
+        let expected = |width: f32, height: f32| {
+            let columns = [
+                (0., 1., 1., (1. - width) / 2.),
+                (1., 2., width - 2., 0.),
+                (2., 3., 1., (width - 1.) / 2.),
+            ];
+            let rows = [
+                (0., 1., 1., (height - 1.) / 2.),
+                (1., 2., height - 2., 0.),
+                (2., 3., 1., (1. - height) / 2.),
+            ];
+            rows.iter()
+                .flat_map(|&(top, bottom, height, y)| {
+                    columns.iter().map(move |&(left, right, width, x)| {
+                        (
+                            Rect::new(left, top, right, bottom),
+                            Vec2::new(width, height),
+                            Vec2::new(x, y),
+                        )
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+        let children = |app: &App| app.world.get::<Children>(entity).unwrap().to_vec();
+        let despawned = |app: &App, entities: &[Entity]| {
+            entities
+                .iter()
+                .all(|entity| app.world.get_entity(*entity).is_none())
+        };
+        assert_eq!(sprites(&app, entity), expected(5., 4.));
+        // sprites are spawned again when the size changes
+        let previous = children(&app);
+        app.world
+            .get_mut::<NinePatchSprite<()>>(entity)
+            .unwrap()
+            .size = Vec2::new(7., 3.);
+        app.update();
+        assert!(despawned(&app, &previous));
+        assert_eq!(sprites(&app, entity), expected(7., 3.));
+        // or when an asset is modified
+        let previous = children(&app);
+        app.world
+            .resource_mut::<Assets<Image>>()
+            .get_mut(&texture)
+            .unwrap();
+        app.update();
+        app.update();
+        assert!(despawned(&app, &previous));
+        assert_eq!(sprites(&app, entity), expected(7., 3.));
+        let previous = children(&app);
+        app.world.entity_mut(entity).remove::<NinePatchSprite<()>>();
+        app.update();
+        assert!(despawned(&app, &previous));
+        assert!(app.world.get::<NinePatchSpriteParts>(entity).is_none());
+    }
+}
@@ -0,0 +1,219 @@
+use bevy::{prelude::*, reflect::TypePath};
+
+use crate::{ninepatch::*, tiles::PatchTiles, NinePatchData};
+
+/// Texture and `NinePatchBuilder` displayed by a 9-Patch UI element in a state
+#[derive(Debug, Clone, PartialEq)]
+pub struct NinePatchSkin<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> {
+    /// Handle of the texture
+    pub texture: Handle<Image>,
+    /// Handle to the `NinePatchBuilder`
+    pub nine_patch: Handle<NinePatchBuilder<T>>,
+}
+
+impl<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> NinePatchSkin<T> {
+    /// Create a skin from a texture and a `NinePatchBuilder`
+    pub fn new(texture: Handle<Image>, nine_patch: Handle<NinePatchBuilder<T>>) -> Self {
+        NinePatchSkin {
+            texture,
+            nine_patch,
+        }
+    }
+}
+
+/// Skins of a 9-Patch UI element depending on its `Interaction`, added next to its `NinePatchData`.
+/// The `NinePatchData` is updated when the `Interaction` or this component changes.
+///
+/// A state without a skin uses the skin of the closest state: pressed falls back to hovered, and
+/// all states fall back to normal.
+#[derive(Debug, Clone, Component)]
+pub struct NinePatchStates<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> {
+    /// Skin when the element is not interacted with
+    pub normal: NinePatchSkin<T>,
+    /// Skin when the element is hovered
+    pub hovered: Option<NinePatchSkin<T>>,
+    /// Skin when the element is pressed
+    pub pressed: Option<NinePatchSkin<T>>,
+    /// Skin when the element is disabled
+    pub disabled: Option<NinePatchSkin<T>>,
+    /// Is the element disabled. A disabled element keeps its disabled skin whatever its
+    /// `Interaction`
+    pub is_disabled: bool,
+    /// Offset of the content of the element while it is pressed, in pixels. It is added to the
+    /// `left` and `top` of the `Style` of entities in content zones when they are in pixels or
+    /// `Auto`, including entities added during the press, and removed when it is released
+    pub pressed_offset: Vec2,
+}
+
+impl<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static> NinePatchStates<T> {
+    /// Create states that always use the `normal` skin
+    pub fn new(normal: NinePatchSkin<T>) -> Self {
+        NinePatchStates {
+            normal,
+            hovered: None,
+            pressed: None,
+            disabled: None,
+            is_disabled: false,
+            pressed_offset: Vec2::ZERO,
+        }
+    }
+
+    /// Set the skin when the element is hovered
+    pub fn with_hovered(mut self, hovered: NinePatchSkin<T>) -> Self {
+        self.hovered = Some(hovered);
+        self
+    }
+
+    /// Set the skin when the element is pressed
+    pub fn with_pressed(mut self, pressed: NinePatchSkin<T>) -> Self {
+        self.pressed = Some(pressed);
+        self
+    }
+
+    /// Set the skin when the element is disabled
+    pub fn with_disabled(mut self, disabled: NinePatchSkin<T>) -> Self {
+        self.disabled = Some(disabled);
+        self
+    }
+
+    /// Set the offset of the content while the element is pressed
+    pub fn with_pressed_offset(mut self, pressed_offset: Vec2) -> Self {
+        self.pressed_offset = pressed_offset;
+        self
+    }
+
+    /// Skin to display with `interaction`
+    pub fn skin(&self, interaction: Interaction) -> &NinePatchSkin<T> {
+        let skin = if self.is_disabled {
+            self.disabled.as_ref()
+        } else {
+            match interaction {
+                Interaction::Pressed => self.pressed.as_ref().or(self.hovered.as_ref()),
+                Interaction::Hovered => self.hovered.as_ref(),
+                Interaction::None => None,
+            }
+        };
+        skin.unwrap_or(&self.normal)
+    }
+
+    /// All the skins of the element
+    pub(crate) fn skins(&self) -> impl Iterator<Item = &NinePatchSkin<T>> {
+        std::iter::once(&self.normal)
+            .chain(&self.hovered)
+            .chain(&self.pressed)
+            .chain(&self.disabled)
+    }
+
+    /// Is the content offset with `interaction`
+    fn is_pressed(&self, interaction: Interaction) -> bool {
+        !self.is_disabled && interaction == Interaction::Pressed
+    }
+}
+
+/// Update the skin of the 9-Patch UI elements with `NinePatchStates`
+#[allow(clippy::type_complexity)]
+pub(crate) fn update_states<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static>(
+    mut nine_patches: Query<
+        (
+            &NinePatchStates<T>,
+            Option<&Interaction>,
+            &mut NinePatchData<T>,
+        ),
+        Or<(Changed<Interaction>, Changed<NinePatchStates<T>>)>,
+    >,
+) {
+    for (states, interaction, mut data) in nine_patches.iter_mut() {
+        let skin = states.skin(interaction.copied().unwrap_or_default());
+        // only update the `NinePatchData` when needed, as changing it can rebuild the nine-patch
+        if data.texture != skin.texture {
+            data.texture = skin.texture.clone();
+        }
+        if data.nine_patch != skin.nine_patch {
+            data.nine_patch = skin.nine_patch.clone();
+        }
+    }
+}
+
+/// Position of an entity in a content zone before the pressed offset was added to it
+#[derive(Debug, Clone, Copy, Component)]
+pub(crate) struct PressedOffset {
+    /// `left` of the `Style` of the entity without the offset
+    left: Val,
+    /// `top` of the `Style` of the entity without the offset
+    top: Val,
+    /// `left` and `top` of the `Style` of the entity with the offset
+    offset: (Val, Val),
+}
+
+/// Add `offset` to a position, when it is in pixels or not set
+fn add_offset(position: Val, offset: f32) -> Val {
+    match position {
+        Val::Auto => Val::Px(offset),
+        Val::Px(value) => Val::Px(value + offset),
+        other => other,
+    }
+}
+
+/// Offset the content of the 9-Patch UI elements with `NinePatchStates` while they are pressed,
+/// including entities added to their content zones during the press
+#[allow(clippy::type_complexity)]
+pub(crate) fn offset_contents<
+    T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static,
+>(
+    mut commands: Commands,
+    nine_patches: Query<(
+        Ref<NinePatchStates<T>>,
+        Option<Ref<Interaction>>,
+        &NinePatchZones<T>,
+    )>,
+    zones: Query<(&Children, Option<&PatchTiles>)>,
+    mut contents: Query<(Entity, &mut Style, Option<&PressedOffset>)>,
+) {
+    for (states, interaction, nine_patch_zones) in nine_patches.iter() {
+        let is_pressed = states.is_pressed(interaction.as_deref().copied().unwrap_or_default());
+        let is_changed = states.is_changed() || interaction.is_some_and(|i| i.is_changed());
+        // content added while pressed must be moved, content is only moved back on changes
+        if !is_pressed && !is_changed {
+            continue;
+        }
+        let children = nine_patch_zones
+            .iter()
+            .filter_map(|(_, zone)| zones.get(zone).ok())
+            .flat_map(|(children, tiles)| {
+                children
+                    .iter()
+                    .filter(move |child| !tiles.is_some_and(|tiles| tiles.tiles.contains(child)))
+            });
+        for child in children {
+            let Ok((entity, mut style, pressed_offset)) = contents.get_mut(*child) else {
+                continue;
+            };
+            // the position without offset, unless it has been changed since the offset was added
+            let (left, top) = match pressed_offset {
+                Some(pressed_offset) if pressed_offset.offset == (style.left, style.top) => {
+                    (pressed_offset.left, pressed_offset.top)
+                }
+                _ => (style.left, style.top),
+            };
+            if is_pressed && states.pressed_offset != Vec2::ZERO {
+                let offset = (
+                    add_offset(left, states.pressed_offset.x),
+                    add_offset(top, states.pressed_offset.y),
+                );
+                if (style.left, style.top) != offset {
+                    (style.left, style.top) = offset;
+                }
+                if pressed_offset.map(|pressed_offset| pressed_offset.offset) != Some(offset) {
+                    commands
+                        .entity(entity)
+                        .insert(PressedOffset { left, top, offset });
+                }
+            } else if pressed_offset.is_some() {
+                if (style.left, style.top) != (left, top) {
+                    (style.left, style.top) = (left, top);
+                }
+                commands.entity(entity).remove::<PressedOffset>();
+            }
+        }
+    }
+}
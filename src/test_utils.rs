@@ -0,0 +1,28 @@
+//! Helpers shared by the tests of the crate
+
+use bevy::{
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+
+/// App with the `Assets<Image>` resource, which must be kept alive to add textures
+pub(crate) fn app() -> App {
+    let mut app = App::new();
+    app.add_plugins((TaskPoolPlugin::default(), AssetPlugin::default()))
+        .add_asset::<Image>();
+    app
+}
+
+/// Texture of `width` by `height` pixels holding `data`
+pub(crate) fn texture(width: u32, height: u32, data: Vec<u8>, format: TextureFormat) -> Image {
+    Image::new(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        format,
+    )
+}
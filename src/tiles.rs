@@ -0,0 +1,95 @@
+use bevy::{prelude::*, ui::FocusPolicy};
+
+use crate::{layout::tiles, ninepatch::PatchStretch};
+
+/// Texture repeated by a tiled patch
+#[derive(Debug, Clone)]
+pub(crate) enum TileTexture {
+    /// Texture of the patch, in the `SplitTextures` render mode
+    Image(Handle<Image>),
+    /// Rectangle of the patch in a `TextureAtlas`, in the `TextureAtlas` render mode
+    Atlas(Handle<TextureAtlas>, usize),
+}
+
+/// Repetitions of a patch that is not stretched, in the `SplitTextures` and `TextureAtlas` render
+/// modes. Added on the patch entity, which cuts the repetitions going past it
+#[derive(Debug, Clone, Component)]
+pub(crate) struct PatchTiles {
+    /// How the patch fills its size
+    pub(crate) stretch: PatchStretch,
+    /// Size of the patch in the texture, in pixels
+    pub(crate) source_size: Vec2,
+    /// Texture of the patch
+    pub(crate) texture: TileTexture,
+    /// Color multiplied with the texture of the repetitions
+    pub(crate) color: Color,
+    /// Size of the node the repetitions were spawned for
+    pub(crate) size: Option<Vec2>,
+    /// Repetition entities, children of the patch entity
+    pub(crate) tiles: Vec<Entity>,
+}
+
+/// Plugin updating the repetitions of tiled patches, shared by all `NinePatchPlugin`
+pub(crate) struct TilesPlugin;
+
+impl Plugin for TilesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, update_tiles.after(bevy::ui::UiSystem::Layout));
+    }
+}
+
+/// Spawn the repetitions of the tiled patches whose node has been resized
+fn update_tiles(mut commands: Commands, mut patches: Query<(Entity, &Node, &mut PatchTiles)>) {
+    for (entity, node, mut patch_tiles) in patches.iter_mut() {
+        let size = node.size();
+        if patch_tiles.size == Some(size) {
+            continue;
+        }
+        for tile in patch_tiles.tiles.drain(..) {
+            commands.entity(tile).despawn_recursive();
+        }
+        let tile_entities: Vec<Entity> = tiles(patch_tiles.stretch, patch_tiles.source_size, size)
+            .into_iter()
+            .map(|tile| {
+                let style = Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(tile.target.min.x),
+                    top: Val::Px(tile.target.min.y),
+                    width: Val::Px(tile.target.width()),
+                    height: Val::Px(tile.target.height()),
+                    ..Default::default()
+                };
+                match &patch_tiles.texture {
+                    TileTexture::Image(texture) => commands.spawn(ImageBundle {
+                        image: UiImage {
+                            texture: texture.clone(),
+                            flip_x: tile.flip_x,
+                            flip_y: tile.flip_y,
+                        },
+                        style,
+                        background_color: BackgroundColor(patch_tiles.color),
+                        focus_policy: FocusPolicy::Pass,
+                        ..Default::default()
+                    }),
+                    TileTexture::Atlas(atlas, index) => commands.spawn(AtlasImageBundle {
+                        texture_atlas: atlas.clone(),
+                        texture_atlas_image: UiTextureAtlasImage {
+                            index: *index,
+                            flip_x: tile.flip_x,
+                            flip_y: tile.flip_y,
+                        },
+                        style,
+                        background_color: BackgroundColor(patch_tiles.color),
+                        focus_policy: FocusPolicy::Pass,
+                        ..Default::default()
+                    }),
+                }
+                .id()
+            })
+            .collect();
+        // repetitions are drawn below the content of the patch
+        commands.entity(entity).insert_children(0, &tile_entities);
+        patch_tiles.tiles = tile_entities;
+        patch_tiles.size = Some(size);
+    }
+}
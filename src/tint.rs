@@ -0,0 +1,50 @@
+use bevy::{prelude::*, reflect::TypePath};
+
+use crate::{
+    ninepatch::NinePatchParts, single_node::NinePatchSlices, tiles::PatchTiles, NinePatchData,
+};
+
+/// Color the patches of the nine-patches whose tint changed, or that have been created
+#[allow(clippy::type_complexity)]
+pub(crate) fn update_tints<T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static>(
+    mut nine_patches: Query<
+        (
+            &NinePatchData<T>,
+            &NinePatchParts<T>,
+            Option<&mut NinePatchSlices>,
+        ),
+        Or<(Changed<NinePatchData<T>>, Changed<NinePatchParts<T>>)>,
+    >,
+    mut colors: Query<&mut BackgroundColor>,
+    mut tiles: Query<&mut PatchTiles>,
+) {
+    for (data, parts, slices) in nine_patches.iter_mut() {
+        let tints: Vec<Color> = parts
+            .patches_layout
+            .iter()
+            .enumerate()
+            .flat_map(|(row, patches)| (0..patches.len()).map(move |column| (row, column)))
+            .map(|(row, column)| data.patch_tint(row, column))
+            .collect();
+        for (patch, tint) in parts.patches.iter().zip(&tints) {
+            // the node of a tiled patch only clips its repetitions, which hold the texture
+            if let Ok(mut patch_tiles) = tiles.get_mut(*patch) {
+                if patch_tiles.color != *tint {
+                    patch_tiles.color = *tint;
+                }
+                for tile in &patch_tiles.tiles {
+                    if let Ok(mut color) = colors.get_mut(*tile) {
+                        color.0 = *tint;
+                    }
+                }
+            } else if let Ok(mut color) = colors.get_mut(*patch) {
+                if color.0 != *tint {
+                    color.0 = *tint;
+                }
+            }
+        }
+        if let Some(mut slices) = slices {
+            slices.set_tints(tints);
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use bevy::{ecs::system::EntityCommands, prelude::*, reflect::TypePath};
+
+use crate::{
+    ninepatch::{GeneratedNode, NinePatchZones},
+    NinePatchData, NinePatchLoadState,
+};
+
+/// Children waiting for a content zone of a 9-Patch UI element. They are spawned under a hidden
+/// node until the zone exists, and despawned if it won't
+#[derive(Debug, Clone, Component)]
+pub(crate) struct PendingZoneChildren<T: Clone + Send + Sync + 'static> {
+    /// Content of the zone
+    content: T,
+}
+
+/// Add children to the content zones of a 9-Patch UI element
+pub trait BuildZoneChildren<T> {
+    /// Spawn children in the content zone for `content` of this 9-Patch UI element. If it is not
+    /// displayed yet, they are hidden and moved to the zone once it is. They are despawned with a
+    /// warning if the nine-patch fails, or has no content zone for `content`.
+    ///
+    /// ```rust
+    /// # use bevy::prelude::*;
+    /// # use bevy_ninepatch::*;
+    /// fn add_title(mut commands: Commands, nine_patches: Query<Entity, Added<NinePatchData<()>>>) {
+    ///     for nine_patch in nine_patches.iter() {
+    ///         commands.entity(nine_patch).with_zone_children((), |zone| {
+    ///             zone.spawn(TextBundle::from_section("Title", TextStyle::default()));
+    ///         });
+    ///     }
+    /// }
+    /// ```
+    fn with_zone_children(
+        &mut self,
+        content: T,
+        spawn_children: impl FnOnce(&mut ChildBuilder),
+    ) -> &mut Self;
+}
+
+impl<'w, 's, 'a, T: Clone + Send + Sync + Eq + std::hash::Hash + 'static> BuildZoneChildren<T>
+    for EntityCommands<'w, 's, 'a>
+{
+    fn with_zone_children(
+        &mut self,
+        content: T,
+        spawn_children: impl FnOnce(&mut ChildBuilder),
+    ) -> &mut Self {
+        let nine_patch = self.id();
+        let pending = self
+            .commands()
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        display: Display::None,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                PendingZoneChildren {
+                    content: content.clone(),
+                },
+                GeneratedNode { nine_patch },
+            ))
+            .with_children(spawn_children)
+            .id();
+        // when the nine-patch is already displayed, the children are moved to the zone right away
+        self.commands().add(move |world: &mut World| {
+            let Some(zone) = world
+                .get::<NinePatchZones<T>>(nine_patch)
+                .and_then(|zones| zones.get(&content))
+                .filter(|zone| world.get_entity(*zone).is_some())
+            else {
+                return;
+            };
+            let children = world
+                .get::<Children>(pending)
+                .map(|children| children.to_vec())
+                .unwrap_or_default();
+            world.entity_mut(zone).push_children(&children);
+            world.entity_mut(pending).despawn();
+        });
+        self
+    }
+}
+
+/// Move children waiting for a content zone to the zone, once it exists. Children whose zone
+/// won't exist are despawned
+#[allow(clippy::type_complexity)]
+pub(crate) fn attach_zone_children<
+    T: Clone + Send + Sync + Eq + std::hash::Hash + TypePath + 'static,
+>(
+    mut commands: Commands,
+    pending: Query<(
+        Entity,
+        &PendingZoneChildren<T>,
+        &GeneratedNode,
+        Option<&Children>,
+    )>,
+    nine_patches: Query<(&NinePatchData<T>, Option<&NinePatchZones<T>>)>,
+) {
+    for (entity, pending, generated, children) in pending.iter() {
+        let nine_patch = generated.nine_patch;
+        let zone = match nine_patches.get(nine_patch) {
+            Err(_) => Err("it has no `NinePatchData`".to_string()),
+            Ok((data, zones)) => match (data.load_state, zones) {
+                (NinePatchLoadState::Failed(error), _) => Err(format!("it failed: {error}")),
+                (NinePatchLoadState::Ready, Some(zones)) => zones
+                    .get(&pending.content)
+                    .ok_or_else(|| "it has no content zone for their content".to_string()),
+                // wait for the nine-patch to be displayed
+                _ => continue,
+            },
+        };
+        match zone {
+            Ok(zone) => {
+                if let Some(children) = children {
+                    commands.entity(zone).push_children(children);
+                }
+                commands.entity(entity).despawn();
+            }
+            Err(reason) => {
+                warn!("despawning zone children of nine-patch {nine_patch:?}, {reason}");
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}